@@ -0,0 +1,257 @@
+use std::path::Path;
+
+use crate::{
+    icon_conversion::IconResolution,
+    magick::CommandArgs,
+    primitives::{Dimensions, Offset},
+    zip::crc32,
+};
+
+/// Every resolution is scaled up to the largest icon size, so each icon pixel becomes a whole
+/// number of frame pixels.
+const FRAME_SIZE: u32 = 1024;
+const FRAME_DELAY_CENTISECONDS: u16 = 50;
+/// GIF only supports binary transparency, so GIF frames are flattened onto a background.
+const GIF_BACKGROUND: &str = "#f5f5f5";
+const LABEL_COLOR: &str = "#3c3c3c";
+const LABEL_POINT_SIZE: u32 = 48;
+const LABEL_MARGIN: i32 = 24;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+// https://wiki.mozilla.org/APNG_Specification
+const APNG_DISPOSE_OP_BACKGROUND: u8 = 1;
+const APNG_BLEND_OP_SOURCE: u8 = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+impl AnimationFormat {
+    pub fn from_path(path: &Path) -> Option<AnimationFormat> {
+        let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+        match extension.as_str() {
+            "gif" => Some(AnimationFormat::Gif),
+            "png" | "apng" => Some(AnimationFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// A single frame: the icon scaled up using nearest-neighbor sampling, labeled with its resolution.
+pub fn frame_args(
+    icon_path: &Path,
+    resolution: &IconResolution,
+    format: AnimationFormat,
+    frame_path: &Path,
+) -> CommandArgs {
+    let mut args = CommandArgs::new();
+    args.push_path(icon_path);
+    args.push("-sample");
+    args.push(&Dimensions::square(FRAME_SIZE).to_string());
+    if format == AnimationFormat::Gif {
+        args.push("-background");
+        args.push(GIF_BACKGROUND);
+        args.flatten();
+    }
+    args.push("-gravity");
+    args.push("south");
+    args.push("-fill");
+    args.push(LABEL_COLOR);
+    args.push("-pointsize");
+    args.push(&LABEL_POINT_SIZE.to_string());
+    args.push("-annotate");
+    args.push(&Offset::from_y(LABEL_MARGIN).to_string());
+    args.push(&resolution.to_string());
+    // All frames of an APNG must have the same pixel format.
+    args.push_string(format!("PNG32:{}", frame_path.display()));
+    args
+}
+
+pub fn gif_args(frame_paths: &[&Path], output_path: &Path) -> CommandArgs {
+    let mut args = CommandArgs::new();
+    args.push("-delay");
+    args.push(&FRAME_DELAY_CENTISECONDS.to_string());
+    args.push("-loop");
+    args.push("0");
+    for frame_path in frame_paths {
+        args.push_path(frame_path);
+    }
+    args.push("-layers");
+    args.push("Optimize");
+    args.push_path(output_path);
+    args
+}
+
+struct PngChunk<'a> {
+    chunk_type: &'a [u8],
+    data: &'a [u8],
+}
+
+fn png_chunks(png: &[u8]) -> Result<Vec<PngChunk<'_>>, String> {
+    let mut rest = png
+        .strip_prefix(PNG_SIGNATURE)
+        .ok_or("An animation frame is not a PNG file.")?;
+    let mut chunks = Vec::<PngChunk>::new();
+    while !rest.is_empty() {
+        let length = rest
+            .get(0..4)
+            .map(|length| u32::from_be_bytes(length.try_into().unwrap()) as usize)
+            .ok_or("An animation frame is truncated.")?;
+        let chunk = rest
+            .get(4..12 + length)
+            .ok_or("An animation frame is truncated.")?;
+        chunks.push(PngChunk {
+            chunk_type: &chunk[0..4],
+            data: &chunk[4..4 + length],
+        });
+        rest = &rest[12 + length..];
+    }
+    Ok(chunks)
+}
+
+fn push_png_chunk(png: &mut Vec<u8>, chunk_type: &[u8], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let type_and_data = [chunk_type, data].concat();
+    png.extend(&type_and_data);
+    png.extend(crc32(&type_and_data).to_be_bytes());
+}
+
+/// Combines PNG frames (with identical headers) into an animated PNG that loops forever.
+pub fn apng(frames: &[Vec<u8>]) -> Result<Vec<u8>, String> {
+    let frame_chunks = frames
+        .iter()
+        .map(|frame| png_chunks(frame))
+        .collect::<Result<Vec<Vec<PngChunk>>, String>>()?;
+    let Some(first_frame) = frame_chunks.first() else {
+        return Err("An animation needs at least one frame.".into());
+    };
+    let header = |chunks: &[PngChunk]| {
+        chunks
+            .iter()
+            .find(|chunk| chunk.chunk_type == b"IHDR")
+            .map(|chunk| chunk.data.to_vec())
+    };
+    let ihdr = header(first_frame).ok_or("An animation frame has no header.")?;
+    if frame_chunks
+        .iter()
+        .any(|chunks| header(chunks) != Some(ihdr.clone()))
+    {
+        return Err("All animation frames must have the same size and pixel format.".into());
+    }
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    push_png_chunk(&mut png, b"IHDR", &ihdr);
+    let mut actl = Vec::<u8>::new();
+    actl.extend((frames.len() as u32).to_be_bytes());
+    // Loop forever.
+    actl.extend(0u32.to_be_bytes());
+    push_png_chunk(&mut png, b"acTL", &actl);
+    // Keep metadata like the color profile from the first frame.
+    for chunk in first_frame
+        .iter()
+        .take_while(|chunk| chunk.chunk_type != b"IDAT")
+        .filter(|chunk| chunk.chunk_type != b"IHDR")
+    {
+        push_png_chunk(&mut png, chunk.chunk_type, chunk.data);
+    }
+
+    let mut sequence_number = 0u32;
+    for (frame_index, chunks) in frame_chunks.iter().enumerate() {
+        let mut fctl = Vec::<u8>::new();
+        fctl.extend(sequence_number.to_be_bytes());
+        // Width and height.
+        fctl.extend(&ihdr[0..8]);
+        // x and y offset.
+        fctl.extend([0; 8]);
+        fctl.extend(FRAME_DELAY_CENTISECONDS.to_be_bytes());
+        fctl.extend(100u16.to_be_bytes());
+        fctl.push(APNG_DISPOSE_OP_BACKGROUND);
+        fctl.push(APNG_BLEND_OP_SOURCE);
+        push_png_chunk(&mut png, b"fcTL", &fctl);
+        sequence_number += 1;
+
+        for chunk in chunks.iter().filter(|chunk| chunk.chunk_type == b"IDAT") {
+            match frame_index {
+                0 => push_png_chunk(&mut png, b"IDAT", chunk.data),
+                _ => {
+                    let fdat = [&sequence_number.to_be_bytes(), chunk.data].concat();
+                    push_png_chunk(&mut png, b"fdAT", &fdat);
+                    sequence_number += 1;
+                }
+            }
+        }
+    }
+    push_png_chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::animation::{apng, png_chunks, push_png_chunk, AnimationFormat, PNG_SIGNATURE};
+
+    fn png(ihdr: &[u8], idats: &[&[u8]]) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        push_png_chunk(&mut png, b"IHDR", ihdr);
+        push_png_chunk(&mut png, b"sRGB", &[0]);
+        for idat in idats {
+            push_png_chunk(&mut png, b"IDAT", idat);
+        }
+        push_png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    #[test]
+    fn test_animation_format() {
+        assert_eq!(
+            AnimationFormat::from_path(Path::new("out.GIF")),
+            Some(AnimationFormat::Gif)
+        );
+        assert_eq!(
+            AnimationFormat::from_path(Path::new("out.apng")),
+            Some(AnimationFormat::Apng)
+        );
+        assert_eq!(AnimationFormat::from_path(Path::new("out.mp4")), None);
+    }
+
+    #[test]
+    fn test_apng() {
+        let ihdr = [0, 0, 0, 2, 0, 0, 0, 2, 8, 6, 0, 0, 0];
+        let animated =
+            apng(&[png(&ihdr, &[b"first"]), png(&ihdr, &[b"second", b"third"])]).unwrap();
+        let chunks = png_chunks(&animated).unwrap();
+        let chunk_types: Vec<&[u8]> = chunks.iter().map(|chunk| chunk.chunk_type).collect();
+        assert_eq!(
+            chunk_types,
+            vec![
+                b"IHDR" as &[u8],
+                b"acTL",
+                b"sRGB",
+                b"fcTL",
+                b"IDAT",
+                b"fcTL",
+                b"fdAT",
+                b"fdAT",
+                b"IEND"
+            ]
+        );
+        assert_eq!(chunks[1].data, [0, 0, 0, 2, 0, 0, 0, 0]);
+        assert_eq!(
+            chunks[3].data,
+            [0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 50, 0, 100, 1, 0]
+        );
+        assert_eq!(chunks[4].data, b"first");
+        assert_eq!(&chunks[5].data[0..4], [0, 0, 0, 1]);
+        assert_eq!(chunks[6].data, b"\0\0\0\x02second");
+        assert_eq!(chunks[7].data, b"\0\0\0\x03third");
+        // The CRC of an empty `IEND` chunk.
+        assert_eq!(&animated[animated.len() - 4..], [0xae, 0x42, 0x60, 0x82]);
+
+        let other_ihdr = [0, 0, 0, 4, 0, 0, 0, 4, 8, 6, 0, 0, 0];
+        assert!(apng(&[png(&ihdr, &[b"a"]), png(&other_ihdr, &[b"b"])]).is_err());
+        assert!(apng(&[]).is_err());
+    }
+}
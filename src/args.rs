@@ -1,18 +1,39 @@
-use clap::{CommandFactory, Parser, ValueEnum};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use clap_complete::generator::generate;
 use clap_complete::{Generator, Shell};
+use std::fs::metadata;
 use std::io::stdout;
 use std::process::exit;
 use std::str::from_utf8;
-use std::{env::var, fmt::Display, path::PathBuf, process::Command};
+use std::{
+    env::var,
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
+use crate::animation::AnimationFormat;
 use crate::build::CLAP_LONG_VERSION;
+use crate::icon_conversion::{IconResolution, CUSTOM_SIZE_RANGE};
+use crate::icon_theme;
+use crate::jobs::set_max_jobs;
+use crate::macos_versions::{
+    lookup_macos_release, release_range_description, MACOS_RELEASES, UNKNOWN_VERSION_FOLDER_STYLE,
+};
+use crate::mask_overrides::{find_mask_overrides, MaskOverride};
+use crate::mask_placement::MaskPlacement;
+use crate::status;
+use crate::status::{set_status_destination, StatusDestination};
+use crate::text_mask::TextMask;
 
 /// Generate a native-style macOS folder icon from a mask file.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-#[clap(name = "folderify", long_version = CLAP_LONG_VERSION )]
+#[clap(name = "folderify", long_version = CLAP_LONG_VERSION, args_conflicts_with_subcommands = true)]
 struct FolderifyArgs {
+    #[command(subcommand)]
+    command: Option<FolderifyCommand>,
+
     #[allow(clippy::doc_lazy_continuation)] // We want concise text.
     /// Mask image file. For best results:
     /// - Use a .png mask.
@@ -20,6 +41,7 @@ struct FolderifyArgs {
     /// - Make sure the corner pixels of the mask image are transparent. They are used for empty margins.
     /// - Make sure the non-transparent pixels span a height of 384px, using a 16px grid.
     /// If the height is 384px and the width is a multiple of 128px, each 64x64 tile will exactly align with 1 pixel at the smallest folder size.
+    /// Use `-` to read the mask from stdin.
     #[clap(verbatim_doc_comment)]
     mask: Option<PathBuf>,
 
@@ -27,35 +49,175 @@ struct FolderifyArgs {
     /// be applied to the target file/folder. Else (unless --output-icns or
     /// --output-iconset is specified), a .iconset folder and .icns file will be
     /// created in the same folder as the mask (you can use "Get Info" in Finder
-    /// to copy the icon from the .icns file).
+    /// to copy the icon from the .icns file). On Linux, only the .iconset folder
+    /// is created.
     #[clap(verbatim_doc_comment)]
     target: Option<PathBuf>,
 
-    /// Write the `.icns` file to the given path.
+    /// Render the given text as the mask (instead of using a mask file), e.g. "API".
+    /// The text is scaled to fit, and can span multiple lines.
+    #[clap(verbatim_doc_comment, long, group = "TEXT_MASK")]
+    text: Option<String>,
+
+    /// Render the given emoji as the mask (instead of using a mask file), e.g. "🚀".
+    #[clap(long, group = "TEXT_MASK")]
+    emoji: Option<String>,
+
+    /// Font file used to render `--text` or `--emoji`.
+    /// Defaults to ImageMagick's default font (or the system emoji font for `--emoji`).
+    #[clap(verbatim_doc_comment, long, requires = "TEXT_MASK", id = "FONT_FILE")]
+    font: Option<PathBuf>,
+
+    /// Folder with hand-tuned masks for specific sizes (e.g. `icon_16x16.png`), in `.iconset` layout.
+    /// Other sizes use the main mask.
+    /// Masks next to the main mask that are named by pixel size (e.g. `mask@16.png` or `mask@32.png` next to `mask.png`) are also used automatically.
+    #[clap(verbatim_doc_comment, long, id = "MASK_ICONSET_FOLDER")]
+    mask_iconset: Option<PathBuf>,
+
+    /// Write the `.icns` file to the given path, or to stdout for `-` (status is then printed to stderr).
     /// (Will be written even if a target is also specified.)
     #[clap(verbatim_doc_comment, long, id = "ICNS_FILE")]
     output_icns: Option<PathBuf>,
 
+    /// Also include legacy chunks for 16x16, 32x32, and 128x128 in `.icns` files (`is32`/`s8mk`, `il32`/`l8mk`, `it32`/`t8mk`).
+    /// These are only needed for older tools that don't read the PNG chunks.
+    #[clap(verbatim_doc_comment, long)]
+    icns_legacy_chunks: bool,
+
+    /// Render light and dark icons, and embed the dark icon in each `.icns` file (instead of writing separate `.dark.icns` files).
+    /// An assigned icon then follows the system appearance (macOS 10.14 and later). Only applies to folder styles with a separate dark mode (e.g. Big Sur).
+    #[clap(verbatim_doc_comment, long, conflicts_with = "color_scheme")]
+    icns_dark_variant: bool,
+
     /// Write the `.iconset` folder to the given path.
     /// (Will be written even if a target is also specified.)
     #[clap(verbatim_doc_comment, long, id = "ICONSET_FOLDER")]
     output_iconset: Option<PathBuf>,
 
+    /// Write a freedesktop icon theme (`hicolor` layout with an `index.theme`) to the given folder.
+    /// (Will be written even if a target is also specified.)
+    #[clap(verbatim_doc_comment, long, id = "ICON_THEME_FOLDER")]
+    output_icon_theme: Option<PathBuf>,
+
+    /// Icon name used for files in the `--output-icon-theme` folder.
+    /// Defaults to "folder-" followed by the mask file name, e.g. "folder-myproject".
+    #[clap(verbatim_doc_comment, long, requires = "ICON_THEME_FOLDER")]
+    icon_name: Option<String>,
+
+    /// Write the icon into the given Xcode asset catalog (e.g. `Assets.xcassets`), as a `.iconset` with a `Contents.json`.
+    /// (Will be written even if a target is also specified.)
+    #[clap(verbatim_doc_comment, long, id = "XCASSETS_FOLDER")]
+    output_xcassets: Option<PathBuf>,
+
+    /// Asset name used for the `--output-xcassets` icon set.
+    /// Defaults to the mask file name.
+    #[clap(
+        verbatim_doc_comment,
+        long = "name",
+        requires = "XCASSETS_FOLDER",
+        id = "ASSET_NAME"
+    )]
+    asset_name: Option<String>,
+
+    /// Write a contact sheet (`.png`) that shows every icon size on light and dark backgrounds, e.g. for reviewing changes.
+    /// Includes every rendered folder style and color scheme. (Will be written even if a target is also specified.)
+    #[clap(verbatim_doc_comment, long, id = "SHEET_PNG")]
+    output_preview: Option<PathBuf>,
+
+    /// Write an animation that cycles through every icon size, scaled up so that individual pixels are visible.
+    /// The format is based on the extension: `.gif`, or `.png`/`.apng` for an animated PNG (which keeps transparency).
+    #[clap(verbatim_doc_comment, long, id = "ANIMATION_FILE")]
+    output_animation: Option<PathBuf>,
+
+    /// Write a single icon size to the given `.png` file, at the size given by a following `--size` (default: "512x512@2x").
+    /// Can be specified multiple times. (Will be written even if a target is also specified.)
+    #[clap(verbatim_doc_comment, long, id = "PNG_FILE")]
+    output_png: Vec<PathBuf>,
+
+    /// Size for the preceding `--output-png`: an icon size like "32x32" or "512@2x", or any size in pixels from 16 to 1024 (e.g. "48").
+    /// Sizes that are not part of an `.iconset` are drawn using parameters interpolated from the closest sizes.
+    #[clap(
+        verbatim_doc_comment,
+        long,
+        value_parser = parse_png_size,
+        requires = "PNG_FILE",
+        id = "PNG_SIZE"
+    )]
+    size: Vec<IconResolution>,
+
     /// Reveal either the target, `.icns`, or `.iconset` (in that order of preference) in Finder.
     #[clap(short, long)]
     reveal: bool,
 
-    /// Version of the macOS folder icon, e.g. "14.2.1".
+    /// Print the rendered icon in the terminal (e.g. to check it over SSH).
+    #[clap(long)]
+    preview: bool,
+
+    /// Icon size shown by `--preview`, e.g. "32x32" or "128x128@2x".
+    #[clap(
+        long,
+        value_parser = parse_icon_resolution,
+        default_value = "256x256",
+        requires = "preview",
+        id = "PREVIEW_SIZE"
+    )]
+    preview_size: IconResolution,
+
+    /// Terminal graphics used by `--preview` — auto detects them from the environment.
+    /// `blocks` (Unicode half blocks) works in any terminal with 24-bit color.
+    #[clap(verbatim_doc_comment, long, value_enum, default_value_t = PreviewProtocol::Auto, requires = "preview")]
+    preview_protocol: PreviewProtocol,
+
+    /// Version of the macOS folder icon, e.g. "14.2.1" or "sonoma".
     /// Defaults to the version currently running.
+    /// Use "all" to render every macOS folder style (e.g. `mask.big-sur.icns` and `mask.tahoe.icns`).
+    /// Run `folderify styles` to list the supported versions.
     #[clap(long = "macOS", alias = "osx", short_alias = 'x', id = "MACOS_VERSION")]
-    mac_os: Option<String>, // TODO: enum, default?
+    mac_os: Option<String>,
+
+    /// Folder style, instead of determining it from the macOS version.
+    /// Use "all" to render every folder style.
+    #[clap(
+        verbatim_doc_comment,
+        long,
+        value_enum,
+        conflicts_with = "MACOS_VERSION"
+    )]
+    folder_style: Option<FolderStyleOrAll>,
 
     /// Render the folder as empty.
     #[clap(long, default_value_t = false)]
     empty_folder: bool,
 
+    /// How the mask is drawn onto the folder.
+    /// `engrave` uses only the shape of the mask, engraved in the folder color.
+    /// `color` places the mask as full-color artwork, with a subtle shadow.
+    #[clap(verbatim_doc_comment, long, value_enum, default_value_t = RenderMode::Engrave)]
+    mode: RenderMode,
+
+    /// Don't add a shadow under the artwork for `--mode color`.
+    #[clap(long)]
+    no_shadow: bool,
+
+    /// Scale the area that the mask is fitted into (e.g. "0.8").
+    #[clap(long, default_value_t = 1.0)]
+    mask_scale: f64,
+
+    /// Move the mask right (or left, if negative), in pixels at the 1024x1024 size.
+    #[clap(long, default_value_t = 0, allow_negative_numbers = true)]
+    mask_offset_x: i32,
+
+    /// Move the mask down (or up, if negative), in pixels at the 1024x1024 size.
+    #[clap(long, default_value_t = 0, allow_negative_numbers = true)]
+    mask_offset_y: i32,
+
+    /// Where to place the mask within its area, if the mask doesn't fill it.
+    #[clap(long, value_enum, default_value_t = MaskAnchor::Center)]
+    mask_anchor: MaskAnchor,
+
     /// Color scheme — auto matches the current system value.
-    #[clap(long, value_enum, default_value_t = ColorSchemeOrAuto::Auto)]
+    /// `both` renders light and dark variants in one run (e.g. `mask.icns` and `mask.dark.icns`).
+    #[clap(verbatim_doc_comment, long, value_enum, default_value_t = ColorSchemeOrAuto::Auto)]
     color_scheme: ColorSchemeOrAuto,
 
     /// Don't trim margins from the mask.
@@ -67,13 +229,32 @@ struct FolderifyArgs {
     #[arg(long)]
     no_progress: bool,
 
+    /// Maximum number of icon sizes that are rendered at the same time (each runs `magick` one step at a time).
+    /// Defaults to the number of CPUs. For `serve` and `daemon`, this is shared by all renders.
+    #[clap(
+        verbatim_doc_comment,
+        long,
+        global = true,
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    jobs: Option<u16>,
+
     /// Program used to set the icon. `osascript` should work in most circumstances, `fileicon` performs more checks, and `Rez` produces smaller but less accurate icons.
+    /// On Linux, `gio` sets the GVFS `metadata::custom-icon` attribute (GNOME and others) and `kde` writes a `.directory` entry.
     #[arg(long, hide(true))]
     set_icon_using: Option<SetIconUsingOrAuto>,
 
-    /// Add a badge to the icon. Currently only supports one badge at a time.
-    #[arg(long)]
-    badge: Option<Badge>,
+    /// Add a badge to the icon, optionally followed by a position (e.g. "locked" or "alias:bottom-right").
+    /// Can be specified multiple times. Badges (including `--badge-image`) are layered in the order they are specified.
+    /// Badges: alias, locked, read-only, shared, cloud, warning, checkmark, private
+    /// Positions: bottom-left (default), bottom-right, top-right, center
+    #[clap(verbatim_doc_comment, long, value_parser = parse_badge_arg, id = "BADGE")]
+    badge: Vec<(Badge, BadgePosition)>,
+
+    /// Add a custom badge from an image file (e.g. `.png` or `.svg`) or a `.iconset` folder, optionally followed by a position (e.g. "archived.png:top-right").
+    /// The image is scaled to the same footprint as the built-in badges. Can be specified multiple times.
+    #[clap(verbatim_doc_comment, long, value_parser = parse_badge_image_arg, id = "BADGE_IMAGE")]
+    badge_image: Vec<(PathBuf, BadgePosition)>,
 
     /// Detailed output. Also sets `--no-progress`.
     #[clap(short, long)]
@@ -88,6 +269,33 @@ struct FolderifyArgs {
     completions: Option<Shell>,
 }
 
+#[derive(Subcommand, Debug)]
+enum FolderifyCommand {
+    /// List the supported folder styles and the macOS versions that use them.
+    Styles,
+    /// Run a local HTTP server that renders icons on demand (`POST /render`).
+    Serve {
+        /// Address to listen on.
+        #[clap(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Port to listen on.
+        #[clap(long, default_value_t = 8080)]
+        port: u16,
+        /// Maximum number of icons that are rendered at the same time.
+        #[clap(long, default_value_t = 2, value_parser = clap::value_parser!(u16).range(1..))]
+        workers: u16,
+    },
+    /// Read JSON-RPC requests (one per line) from stdin and write responses and progress to stdout.
+    Daemon,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    pub host: String,
+    pub port: u16,
+    pub workers: u16,
+}
+
 #[derive(ValueEnum, Clone, Debug, PartialEq, Copy)]
 pub enum ColorScheme {
     Light,
@@ -98,6 +306,109 @@ pub enum ColorScheme {
 pub enum Badge {
     Alias,
     Locked,
+    ReadOnly,
+    Shared,
+    Cloud,
+    Warning,
+    Checkmark,
+    Private,
+}
+
+#[derive(ValueEnum, Clone, Debug, PartialEq, Copy)]
+pub enum RenderMode {
+    Engrave,
+    Color,
+}
+
+#[derive(ValueEnum, Clone, Debug, PartialEq, Copy)]
+pub enum MaskAnchor {
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(ValueEnum, Clone, Debug, PartialEq, Copy, Default)]
+pub enum BadgePosition {
+    #[default]
+    BottomLeft,
+    BottomRight,
+    TopRight,
+    Center,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BadgeSource {
+    BuiltIn(Badge),
+    Image(PathBuf),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BadgeSpec {
+    pub source: BadgeSource,
+    pub position: BadgePosition,
+}
+
+fn parse_icon_resolution(s: &str) -> Result<IconResolution, String> {
+    IconResolution::from_name(s).ok_or_else(|| {
+        format!(
+            "expected one of: {}",
+            IconResolution::values()
+                .iter()
+                .map(|resolution| resolution.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    })
+}
+
+fn parse_png_size(s: &str) -> Result<IconResolution, String> {
+    if let Some(resolution) = IconResolution::from_name(s) {
+        return Ok(resolution);
+    }
+    let invalid = || {
+        format!(
+            "expected an icon size like \"32x32\" or \"512@2x\", or a size in pixels from {} to {}",
+            CUSTOM_SIZE_RANGE.start(),
+            CUSTOM_SIZE_RANGE.end()
+        )
+    };
+    if let Some(base_size) = s.strip_suffix("@2x") {
+        return IconResolution::from_name(&format!("{}x{}@2x", base_size, base_size))
+            .ok_or_else(invalid);
+    }
+    let size = match s.split_once('x') {
+        Some((width, height)) if width == height => width,
+        Some(_) => return Err(invalid()),
+        None => s,
+    };
+    size.parse::<u32>()
+        .ok()
+        .and_then(IconResolution::from_pixel_size)
+        .ok_or_else(invalid)
+}
+
+fn parse_badge_arg(s: &str) -> Result<(Badge, BadgePosition), String> {
+    let (badge, position) = match s.split_once(':') {
+        Some((badge, position)) => (badge, BadgePosition::from_str(position, true)?),
+        None => (s, BadgePosition::default()),
+    };
+    Ok((Badge::from_str(badge, true)?, position))
+}
+
+// Only a valid position is split off, so that paths containing `:` still work.
+fn parse_badge_image_arg(s: &str) -> Result<(PathBuf, BadgePosition), String> {
+    if let Some((path, position)) = s.rsplit_once(':') {
+        if let Ok(position) = BadgePosition::from_str(position, true) {
+            return Ok((path.into(), position));
+        }
+    }
+    Ok((s.into(), BadgePosition::default()))
 }
 
 impl Display for ColorScheme {
@@ -118,6 +429,7 @@ enum ColorSchemeOrAuto {
     Auto,
     Light,
     Dark,
+    Both,
 }
 
 #[derive(ValueEnum, Clone, Debug, PartialEq)]
@@ -126,6 +438,18 @@ pub enum SetIconUsing {
     Osascript,
     #[clap(name = "Rez")]
     Rez,
+    Gio,
+    Kde,
+}
+
+impl SetIconUsing {
+    /// Whether the icon is assigned from the `.icns` file (macOS) rather than from a `.png` (freedesktop).
+    pub fn assigns_icns(&self) -> bool {
+        match self {
+            SetIconUsing::Fileicon | SetIconUsing::Osascript | SetIconUsing::Rez => true,
+            SetIconUsing::Gio | SetIconUsing::Kde => false,
+        }
+    }
 }
 
 #[derive(ValueEnum, Clone, Debug, PartialEq)]
@@ -135,45 +459,252 @@ enum SetIconUsingOrAuto {
     Osascript,
     #[clap(name = "Rez")]
     Rez,
+    Gio,
+    Kde,
 }
 
 #[derive(Debug, Clone)]
 pub struct Options {
+    /// For a text mask, this stands in for a mask path when naming outputs.
     pub mask_path: PathBuf,
+    pub text_mask: Option<TextMask>,
+    /// The color scheme of the icon that is assigned (if there is a target).
     pub color_scheme: ColorScheme,
+    /// All color schemes to render.
+    pub color_schemes: Vec<ColorScheme>,
     pub no_trim: bool,
     pub target: Option<PathBuf>,
+    /// The folder style of the icon that is assigned (if there is a target).
     pub folder_style: FolderStyle,
+    /// All folder styles to render.
+    pub folder_styles: Vec<FolderStyle>,
     pub empty_folder: bool,
+    pub mode: RenderMode,
+    /// Only used for `RenderMode::Color`.
+    pub shadow: bool,
+    pub mask_placement: MaskPlacement,
+    pub mask_overrides: Vec<MaskOverride>,
     pub output_icns: Option<PathBuf>,
+    pub icns_legacy_chunks: bool,
+    /// Dark variants are only rendered to be embedded in the light `.icns` file.
+    pub icns_dark_variant: bool,
     pub output_iconset: Option<PathBuf>,
+    pub output_icon_theme: Option<PathBuf>,
+    pub icon_name: String,
+    pub output_xcassets: Option<PathBuf>,
+    pub asset_name: String,
+    pub output_preview: Option<PathBuf>,
+    pub output_animation: Option<(PathBuf, AnimationFormat)>,
+    pub output_pngs: Vec<(PathBuf, IconResolution)>,
     pub set_icon_using: SetIconUsing,
     pub show_progress: bool,
-    pub badge: Option<Badge>,
+    /// In layering order.
+    pub badges: Vec<BadgeSpec>,
     pub reveal: bool,
+    pub preview: Option<PreviewOptions>,
     pub verbose: bool,
     pub debug: bool,
 }
 
-fn completions_for_shell(cmd: &mut clap::Command, generator: impl Generator) {
-    generate(generator, cmd, "folderify", &mut stdout());
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum PreviewProtocol {
+    Auto,
+    /// The kitty graphics protocol (also supported by Ghostty and WezTerm).
+    Kitty,
+    /// iTerm2 inline images (also supported by WezTerm).
+    Iterm2,
+    Sixel,
+    Blocks,
 }
 
-fn is_major_macos_version_one_of(mac_os: &str, versions: &[&str]) -> bool {
-    for major_version_string in versions {
-        if mac_os.starts_with(&format!("{}.", major_version_string))
-            || mac_os == *major_version_string
-        {
-            return true;
+#[derive(Debug, Clone)]
+pub struct PreviewOptions {
+    pub resolution: IconResolution,
+    pub protocol: PreviewProtocol,
+}
+
+/// A single combination of folder style and color scheme to render.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IconVariant {
+    pub folder_style: FolderStyle,
+    pub color_scheme: ColorScheme,
+    /// Added to output names (e.g. `mask.dark.icns`) when more than one variant is rendered.
+    pub name_suffix: Option<String>,
+}
+
+impl Options {
+    pub fn mask_is_stdin(&self) -> bool {
+        self.text_mask.is_none() && self.mask_path == Path::new(STDIO_PATH)
+    }
+
+    pub fn icns_to_stdout(&self) -> bool {
+        self.output_icns.as_deref() == Some(Path::new(STDIO_PATH))
+    }
+
+    pub fn mask_override(&self, resolution: &IconResolution) -> Option<&Path> {
+        self.mask_overrides
+            .iter()
+            .find(|mask_override| &mask_override.resolution == resolution)
+            .map(|mask_override| mask_override.path.as_path())
+    }
+
+    /// Sizes for outputs that are not part of an `.iconset`, and need to be rendered separately.
+    pub fn custom_resolutions(&self) -> Vec<IconResolution> {
+        let mut resolutions: Vec<IconResolution> = self
+            .output_pngs
+            .iter()
+            .map(|(_, resolution)| *resolution)
+            .collect();
+        if self.output_icon_theme.is_some() {
+            resolutions.extend(icon_theme::resolutions());
+        }
+        let mut custom_resolutions = Vec::<IconResolution>::new();
+        for resolution in resolutions {
+            let resolution = resolution.rendered_resolution();
+            if !resolution.is_iconset_resolution() && !custom_resolutions.contains(&resolution) {
+                custom_resolutions.push(resolution);
+            }
+        }
+        custom_resolutions
+    }
+
+    pub fn variants(&self) -> Vec<IconVariant> {
+        let mut variants = Vec::<IconVariant>::new();
+        for &folder_style in &self.folder_styles {
+            for color_scheme in self.color_schemes_for(folder_style) {
+                let mut name_suffix_parts = Vec::<&str>::new();
+                if self.folder_styles.len() > 1 {
+                    name_suffix_parts.push(folder_style.name_suffix());
+                }
+                if self.color_schemes.len() > 1 && color_scheme == ColorScheme::Dark {
+                    name_suffix_parts.push("dark");
+                }
+                variants.push(IconVariant {
+                    folder_style,
+                    color_scheme,
+                    name_suffix: match name_suffix_parts.is_empty() {
+                        true => None,
+                        false => Some(name_suffix_parts.join(".")),
+                    },
+                });
+            }
+        }
+        variants
+    }
+
+    fn color_schemes_for(&self, folder_style: FolderStyle) -> Vec<ColorScheme> {
+        if folder_style.dark_mode_and_light_mode_are_identical() {
+            return vec![ColorScheme::Light];
+        }
+        self.color_schemes.clone()
+    }
+
+    /// Whether the variant is only rendered to be embedded in another `.icns` file (`--icns-dark-variant`).
+    pub fn is_embedded_dark_variant(&self, variant: &IconVariant) -> bool {
+        self.icns_dark_variant && variant.color_scheme == ColorScheme::Dark
+    }
+
+    /// The variant that is assigned to the target.
+    pub fn primary_variant(&self) -> IconVariant {
+        let color_scheme = match self.folder_style.dark_mode_and_light_mode_are_identical() {
+            true => ColorScheme::Light,
+            false => self.color_scheme,
+        };
+        self.variants()
+            .into_iter()
+            .find(|variant| {
+                variant.folder_style == self.folder_style && variant.color_scheme == color_scheme
+            })
+            .expect("The assigned variant is always rendered.")
+    }
+}
+
+// Pairs each `--output-png` with the `--size` that follows it (before the next `--output-png`).
+fn pngs_in_order(
+    matches: &ArgMatches,
+    output_png: Vec<PathBuf>,
+    size: Vec<IconResolution>,
+) -> Result<Vec<(PathBuf, IconResolution)>, String> {
+    let png_indices: Vec<usize> = matches
+        .indices_of("PNG_FILE")
+        .into_iter()
+        .flatten()
+        .collect();
+    let mut sizes: Vec<Option<IconResolution>> = vec![None; output_png.len()];
+    for (index, resolution) in matches
+        .indices_of("PNG_SIZE")
+        .into_iter()
+        .flatten()
+        .zip(size)
+    {
+        let Some(png_index) = png_indices.iter().rposition(|png_index| *png_index < index) else {
+            return Err("`--size` must follow the `--output-png` that it applies to.".into());
+        };
+        if sizes[png_index].replace(resolution).is_some() {
+            return Err(format!(
+                "more than one `--size` for `--output-png`: {}",
+                output_png[png_index].display()
+            ));
         }
     }
-    false
+    Ok(output_png
+        .into_iter()
+        .zip(sizes)
+        .map(|(path, resolution)| (path, resolution.unwrap_or(IconResolution::Retina512)))
+        .collect())
+}
+
+// Interleaves `--badge` and `--badge-image` in the order they were passed.
+fn badges_in_order(
+    matches: &ArgMatches,
+    badge: Vec<(Badge, BadgePosition)>,
+    badge_image: Vec<(PathBuf, BadgePosition)>,
+) -> Vec<BadgeSpec> {
+    let mut indexed_badges = Vec::<(usize, BadgeSpec)>::new();
+    for (index, (badge, position)) in matches.indices_of("BADGE").into_iter().flatten().zip(badge) {
+        indexed_badges.push((
+            index,
+            BadgeSpec {
+                source: BadgeSource::BuiltIn(badge),
+                position,
+            },
+        ));
+    }
+    for (index, (path, position)) in matches
+        .indices_of("BADGE_IMAGE")
+        .into_iter()
+        .flatten()
+        .zip(badge_image)
+    {
+        indexed_badges.push((
+            index,
+            BadgeSpec {
+                source: BadgeSource::Image(path),
+                position,
+            },
+        ));
+    }
+    indexed_badges.sort_by_key(|(index, _)| *index);
+    indexed_badges.into_iter().map(|(_, badge)| badge).collect()
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+fn completions_for_shell(cmd: &mut clap::Command, generator: impl Generator) {
+    generate(generator, cmd, "folderify", &mut stdout());
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum FolderStyle {
     BigSur,
     Tahoe,
+    Adwaita,
+}
+
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+enum FolderStyleOrAll {
+    BigSur,
+    Tahoe,
+    Adwaita,
+    All,
 }
 
 impl Display for FolderStyle {
@@ -181,107 +712,391 @@ impl Display for FolderStyle {
         match self {
             FolderStyle::BigSur => write!(f, "Big Sur"),
             FolderStyle::Tahoe => write!(f, "Tahoe"),
+            FolderStyle::Adwaita => write!(f, "Adwaita"),
         }
     }
 }
 
 impl FolderStyle {
+    pub fn values() -> Vec<FolderStyle> {
+        vec![
+            FolderStyle::BigSur,
+            FolderStyle::Tahoe,
+            FolderStyle::Adwaita,
+        ]
+    }
+
+    /// The folder styles rendered by `--macOS all`.
+    pub fn macos_values() -> Vec<FolderStyle> {
+        vec![FolderStyle::BigSur, FolderStyle::Tahoe]
+    }
+
+    /// Added to output names when more than one folder style is rendered.
+    pub fn name_suffix(&self) -> &'static str {
+        match self {
+            FolderStyle::BigSur => "big-sur",
+            FolderStyle::Tahoe => "tahoe",
+            FolderStyle::Adwaita => "adwaita",
+        }
+    }
+
     pub fn dark_mode_and_light_mode_are_identical(&self) -> bool {
         match self {
             FolderStyle::BigSur => false,
             FolderStyle::Tahoe => true,
+            FolderStyle::Adwaita => true,
         }
     }
 }
 
-pub fn get_options() -> Options {
+pub enum Invocation {
+    Render(Box<Options>),
+    Serve(ServeOptions),
+    Daemon,
+}
+
+pub fn get_invocation() -> Invocation {
     let mut command = FolderifyArgs::command();
 
-    let args = FolderifyArgs::parse();
+    let matches = command.clone().get_matches();
+    let args = FolderifyArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
     if let Some(shell) = args.completions {
         completions_for_shell(&mut command, shell);
         exit(0);
     }
-
-    let mask = match args.mask {
-        Some(mask) => mask,
-        None => {
-            command.print_help().unwrap();
+    set_max_jobs(args.jobs.map(usize::from));
+    match args.command {
+        Some(FolderifyCommand::Styles) => {
+            print_styles();
             exit(0);
         }
+        Some(FolderifyCommand::Serve {
+            host,
+            port,
+            workers,
+        }) => {
+            return Invocation::Serve(ServeOptions {
+                host,
+                port,
+                workers,
+            });
+        }
+        Some(FolderifyCommand::Daemon) => return Invocation::Daemon,
+        None => {}
+    }
+    if args.mask.is_none() && args.text.is_none() && args.emoji.is_none() {
+        command.print_help().unwrap();
+        exit(0);
+    }
+    // Keep stdout clean for the `.icns` file, starting with status output while parsing. This is
+    // only set for the command line, since it applies to the whole process.
+    if args.output_icns.as_deref() == Some(Path::new(STDIO_PATH)) {
+        set_status_destination(StatusDestination::Stderr);
+    }
+
+    match options_from_args(args, &matches) {
+        Ok(options) => Invocation::Render(Box::new(options)),
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            exit(1);
+        }
+    }
+}
+
+/// Parses options from a full argument list (including the program name), without exiting on errors.
+pub fn try_options_from<I, T>(args: I) -> Result<Options, String>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    let matches = FolderifyArgs::command()
+        .try_get_matches_from(args)
+        .map_err(|e| e.to_string())?;
+    let args = FolderifyArgs::from_arg_matches(&matches).map_err(|e| e.to_string())?;
+    if args.command.is_some() {
+        return Err("subcommands are not supported here.".into());
+    }
+    options_from_args(args, &matches)
+}
+
+fn options_from_args(args: FolderifyArgs, matches: &ArgMatches) -> Result<Options, String> {
+    let text_mask = match (args.text, args.emoji) {
+        (Some(text), _) => Some(TextMask {
+            text,
+            emoji: false,
+            font: args.font,
+        }),
+        (None, Some(emoji)) => Some(TextMask {
+            text: emoji,
+            emoji: true,
+            font: args.font,
+        }),
+        (None, None) => None,
+    };
+    let (mask, target) = match (&text_mask, args.mask, args.target) {
+        // With a text mask, the only positional argument is the target.
+        (Some(text_mask), target, None) => (PathBuf::from(text_mask.file_stem()), target),
+        (Some(_), Some(_), Some(_)) => {
+            return Err("a mask file cannot be combined with `--text` or `--emoji`.".into());
+        }
+        (None, Some(mask), target) => (mask, target),
+        (_, None, _) => return Err("a mask file (or `--text` or `--emoji`) is required.".into()),
     };
+    let mask_is_stdin = text_mask.is_none() && mask == Path::new(STDIO_PATH);
+    if mask_is_stdin
+        && target.is_none()
+        && args.output_icns.is_none()
+        && args.output_iconset.is_none()
+        && args.output_icon_theme.is_none()
+        && args.output_xcassets.is_none()
+        && args.output_preview.is_none()
+        && args.output_animation.is_none()
+        && args.output_png.is_empty()
+    {
+        return Err(
+            "reading the mask from stdin requires a target or an output (e.g. `--output-icns -`)."
+                .into(),
+        );
+    }
+    if let Some(font) = text_mask
+        .as_ref()
+        .and_then(|text_mask| text_mask.font.as_ref())
+    {
+        if metadata(font).is_err() {
+            return Err(format!("font does not exist: {}", font.display()));
+        }
+    }
 
-    let folder_style = {
-        let mac_os: String = args
-            .mac_os
-            .map(|s| s.to_owned())
-            .unwrap_or_else(current_macOS_version);
-        let mac_os = mac_os.as_str();
-        // macOS 11.0 reports itself as macOS 10.16 in some APIs. Someone might pass such a value on to `folderify`, so we can't just check for major version 10.
-        // Instead, we denylist the versions that previously had different folder icons, so that we don't accidentally apply the Big Sur style when one of these versions was specified.
-        if matches!(
-            mac_os,
-            "10.5"
-                | "10.6"
-                | "10.7"
-                | "10.8"
-                | "10.9"
-                | "10.10"
-                | "10.11"
-                | "10.12"
-                | "10.13"
-                | "10.14"
-                | "10.15"
-        ) {
-            eprintln!("Error: OS X / macOS 10 was specified. This is no longer supported by folderify v3.\nTo generate these icons, please use folderify v2: https://github.com/lgarron/folderify/tree/main#os-x-macos-10");
-            exit(1)
+    let (folder_style, folder_styles) = match (args.folder_style, args.mac_os.as_deref()) {
+        (Some(FolderStyleOrAll::BigSur), _) => (FolderStyle::BigSur, vec![FolderStyle::BigSur]),
+        (Some(FolderStyleOrAll::Tahoe), _) => (FolderStyle::Tahoe, vec![FolderStyle::Tahoe]),
+        (Some(FolderStyleOrAll::Adwaita), _) => (FolderStyle::Adwaita, vec![FolderStyle::Adwaita]),
+        (Some(FolderStyleOrAll::All), _) => (default_folder_style()?, FolderStyle::values()),
+        (None, None) => {
+            let folder_style = default_folder_style()?;
+            (folder_style, vec![folder_style])
         }
-        if is_major_macos_version_one_of(mac_os, &["15", "14", "13", "12", "11"]) {
-            FolderStyle::BigSur
-        } else if is_major_macos_version_one_of(
-            mac_os,
-            &["26"], // Note: macOS 16 through 25 do not exist.
-        ) {
-            eprintln!("Warning: macOS Tahoe is still in beta. The icon may not match the final macOS 26 release.");
-            FolderStyle::Tahoe
-        } else {
-            eprintln!(
-                "Warning: Unknown macOS version specified. Assuming Big Sur (macOS 11 through 15)."
-            );
-            FolderStyle::BigSur
+        (None, Some(ALL_MACOS_VERSIONS)) => (
+            folder_style_for_macos_version(&current_macOS_version())?,
+            FolderStyle::macos_values(),
+        ),
+        (None, Some(mac_os)) => {
+            let folder_style = folder_style_for_macos_version(mac_os)?;
+            (folder_style, vec![folder_style])
+        }
+    };
+    let output_animation = match args.output_animation {
+        Some(path) => match AnimationFormat::from_path(&path) {
+            Some(format) => Some((path, format)),
+            None => {
+                return Err(format!(
+                    "unsupported animation format (expected `.gif`, `.png`, or `.apng`): {}",
+                    path.display()
+                ))
+            }
+        },
+        None => None,
+    };
+    if let Some(mask_iconset) = &args.mask_iconset {
+        if !mask_iconset.is_dir() {
+            return Err(format!(
+                "mask iconset folder does not exist: {}",
+                mask_iconset.display()
+            ));
         }
+    }
+    let mask_overrides = find_mask_overrides(&mask, args.mask_iconset.as_deref());
+    let mask_placement = MaskPlacement {
+        scale: args.mask_scale,
+        offset_x: args.mask_offset_x,
+        offset_y: args.mask_offset_y,
+        anchor: args.mask_anchor,
     };
+    mask_placement.validate()?;
+    let badges = badges_in_order(matches, args.badge, args.badge_image);
+    let output_pngs = pngs_in_order(matches, args.output_png, args.size)?;
+    for badge in &badges {
+        if let BadgeSource::Image(badge_image) = &badge.source {
+            if metadata(badge_image).is_err() {
+                return Err(format!(
+                    "badge image does not exist: {}",
+                    badge_image.display()
+                ));
+            }
+        }
+    }
     let debug = var("FOLDERIFY_DEBUG") == Ok("1".into());
     let verbose = args.verbose || debug;
     let show_progress = !args.no_progress && !args.verbose;
     let set_icon_using = match args.set_icon_using {
         Some(SetIconUsingOrAuto::Rez) => SetIconUsing::Rez,
         Some(SetIconUsingOrAuto::Fileicon) => SetIconUsing::Fileicon,
+        Some(SetIconUsingOrAuto::Gio) => SetIconUsing::Gio,
+        Some(SetIconUsingOrAuto::Kde) => SetIconUsing::Kde,
+        Some(SetIconUsingOrAuto::Osascript) => SetIconUsing::Osascript,
+        _ if cfg!(target_os = "linux") => SetIconUsing::Gio,
         _ => SetIconUsing::Osascript,
     };
-    Options {
+    let mask_name: String = match mask_is_stdin {
+        true => STDIN_MASK_NAME.into(),
+        false => mask
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into(),
+    };
+    let icon_name = args
+        .icon_name
+        .unwrap_or_else(|| format!("folder-{}", mask_name));
+    let asset_name = args.asset_name.unwrap_or(mask_name);
+    let dark_mode_and_light_mode_are_identical = folder_styles
+        .iter()
+        .all(|folder_style| folder_style.dark_mode_and_light_mode_are_identical());
+    if args.icns_dark_variant && dark_mode_and_light_mode_are_identical {
+        eprintln!("Dark and light mode folder icons are identical for the folder style of this macOS version. Ignoring the `--icns-dark-variant` argument.");
+    }
+    let icns_dark_variant = args.icns_dark_variant && !dark_mode_and_light_mode_are_identical;
+    let (color_scheme, color_schemes) = match (icns_dark_variant, &args.color_scheme) {
+        // The light icon is the main icon of the `.icns` file.
+        (true, _) => (
+            ColorScheme::Light,
+            vec![ColorScheme::Light, ColorScheme::Dark],
+        ),
+        (false, ColorSchemeOrAuto::Both) if !dark_mode_and_light_mode_are_identical => (
+            map_color_scheme_auto(args.color_scheme.clone(), &folder_styles),
+            vec![ColorScheme::Light, ColorScheme::Dark],
+        ),
+        (false, _) => {
+            let color_scheme = map_color_scheme_auto(args.color_scheme.clone(), &folder_styles);
+            (color_scheme, vec![color_scheme])
+        }
+    };
+    let options = Options {
         mask_path: mask,
-        color_scheme: map_color_scheme_auto(args.color_scheme, folder_style),
+        text_mask,
+        color_scheme,
+        color_schemes,
         no_trim: args.no_trim,
-        target: args.target,
+        target,
         folder_style,
+        folder_styles,
         empty_folder: args.empty_folder,
+        mode: args.mode,
+        shadow: !args.no_shadow,
+        mask_placement,
+        mask_overrides,
         output_icns: args.output_icns,
+        icns_legacy_chunks: args.icns_legacy_chunks,
+        icns_dark_variant,
         output_iconset: args.output_iconset,
-        badge: args.badge,
+        output_icon_theme: args.output_icon_theme,
+        icon_name,
+        output_xcassets: args.output_xcassets,
+        asset_name,
+        output_preview: args.output_preview,
+        output_animation,
+        output_pngs,
+        badges,
         set_icon_using,
         show_progress,
         reveal: args.reveal,
+        preview: args.preview.then_some(PreviewOptions {
+            resolution: args.preview_size,
+            protocol: args.preview_protocol,
+        }),
         verbose,
         debug,
+    };
+    if options.icns_to_stdout()
+        && options
+            .variants()
+            .iter()
+            .filter(|variant| !options.is_embedded_dark_variant(variant))
+            .count()
+            > 1
+    {
+        return Err("`--output-icns -` can only write a single icon (it cannot be combined with rendering multiple color schemes or folder styles).".into());
+    }
+    Ok(options)
+}
+
+/// Used for a mask read from stdin, or an `.icns` file written to stdout.
+pub const STDIO_PATH: &str = "-";
+// Used instead of the mask file name when reading the mask from stdin.
+const STDIN_MASK_NAME: &str = "mask";
+
+const ALL_MACOS_VERSIONS: &str = "all";
+
+fn folder_style_for_macos_version(mac_os: &str) -> Result<FolderStyle, String> {
+    match lookup_macos_release(mac_os) {
+        Some(release) => {
+            let Some(folder_style) = release.folder_style else {
+                return Err("OS X / macOS 10 was specified. This is no longer supported by folderify v3.\nTo generate these icons, please use folderify v2: https://github.com/lgarron/folderify/tree/main#os-x-macos-10".into());
+            };
+            if let Some(beta_warning) = release.beta_warning() {
+                eprintln!("{}", beta_warning);
+            }
+            Ok(folder_style)
+        }
+        None => {
+            eprintln!(
+                "Warning: Unknown macOS version specified. Assuming {} ({}).",
+                UNKNOWN_VERSION_FOLDER_STYLE,
+                release_range_description(UNKNOWN_VERSION_FOLDER_STYLE).unwrap_or_default()
+            );
+            Ok(UNKNOWN_VERSION_FOLDER_STYLE)
+        }
+    }
+}
+
+// The style used when a folder style is not determined by an argument.
+fn default_folder_style() -> Result<FolderStyle, String> {
+    if cfg!(target_os = "linux") {
+        // There is no macOS version to match on Linux, so we use the freedesktop (GNOME) style.
+        return Ok(FolderStyle::Adwaita);
+    }
+    folder_style_for_macos_version(&current_macOS_version())
+}
+
+fn print_styles() {
+    for folder_style in FolderStyle::values() {
+        let name = folder_style
+            .to_possible_value()
+            .expect("Folder styles are never skipped.");
+        println!("{} ({})", name.get_name(), folder_style);
+        let mut has_releases = false;
+        for release in MACOS_RELEASES
+            .iter()
+            .filter(|release| release.folder_style == Some(folder_style))
+        {
+            has_releases = true;
+            println!("{}", release.styles_line());
+        }
+        if !has_releases {
+            println!("  Linux (freedesktop)");
+        }
+    }
+    let unsupported: Vec<&str> = MACOS_RELEASES
+        .iter()
+        .filter(|release| release.folder_style.is_none())
+        .map(|release| release.version)
+        .collect();
+    if let (Some(first), Some(last)) = (unsupported.first(), unsupported.last()) {
+        println!(
+            "\nOS X / macOS {} through {} are only supported by folderify v2.",
+            first, last
+        );
     }
 }
 
 fn map_color_scheme_auto(
     color_scheme: ColorSchemeOrAuto,
-    folder_style: FolderStyle,
+    folder_styles: &[FolderStyle],
 ) -> ColorScheme {
-    if folder_style.dark_mode_and_light_mode_are_identical() {
+    if folder_styles
+        .iter()
+        .all(|folder_style| folder_style.dark_mode_and_light_mode_are_identical())
+    {
         match color_scheme {
             ColorSchemeOrAuto::Auto => {}
             _ => {
@@ -294,7 +1109,8 @@ fn map_color_scheme_auto(
     match color_scheme {
         ColorSchemeOrAuto::Dark => return ColorScheme::Dark,
         ColorSchemeOrAuto::Light => return ColorScheme::Light,
-        ColorSchemeOrAuto::Auto => (),
+        // For `both`, the current system value determines which icon is assigned.
+        ColorSchemeOrAuto::Auto | ColorSchemeOrAuto::Both => (),
     };
 
     match Command::new("/usr/bin/env")
@@ -309,7 +1125,7 @@ fn map_color_scheme_auto(
             }
         }
         Err(_) => {
-            println!("Could not compute auto color scheme. Assuming light mode.");
+            status!("Could not compute auto color scheme. Assuming light mode.");
             ColorScheme::Light
         }
     }
@@ -341,4 +1157,111 @@ mod tests {
 
         FolderifyArgs::command().debug_assert();
     }
+
+    #[test]
+    fn test_parse_badge_args() {
+        use crate::args::{parse_badge_arg, parse_badge_image_arg, Badge, BadgePosition};
+        use std::path::PathBuf;
+
+        assert_eq!(
+            parse_badge_arg("locked"),
+            Ok((Badge::Locked, BadgePosition::BottomLeft))
+        );
+        assert_eq!(
+            parse_badge_arg("alias:top-right"),
+            Ok((Badge::Alias, BadgePosition::TopRight))
+        );
+        assert_eq!(
+            parse_badge_arg("read-only:bottom-right"),
+            Ok((Badge::ReadOnly, BadgePosition::BottomRight))
+        );
+        assert!(parse_badge_arg("alias:nowhere").is_err());
+        assert_eq!(
+            parse_badge_image_arg("team.png:center"),
+            Ok((PathBuf::from("team.png"), BadgePosition::Center))
+        );
+        assert_eq!(
+            parse_badge_image_arg("a:b.png"),
+            Ok((PathBuf::from("a:b.png"), BadgePosition::BottomLeft))
+        );
+    }
+
+    #[test]
+    fn test_parse_png_size() {
+        use crate::args::parse_png_size;
+        use crate::icon_conversion::IconResolution;
+
+        assert_eq!(parse_png_size("32x32"), Ok(IconResolution::NonRetina32));
+        assert_eq!(parse_png_size("512@2x"), Ok(IconResolution::Retina512));
+        assert_eq!(parse_png_size("128x128@2x"), Ok(IconResolution::Retina128));
+        assert_eq!(parse_png_size("64"), Ok(IconResolution::Retina32));
+        assert_eq!(parse_png_size("48"), Ok(IconResolution::Custom(48)));
+        assert_eq!(parse_png_size("96x96"), Ok(IconResolution::Custom(96)));
+        assert!(parse_png_size("48@2x").is_err());
+        assert!(parse_png_size("48x32").is_err());
+        assert!(parse_png_size("4096").is_err());
+    }
+
+    #[test]
+    fn test_pngs_in_order() {
+        use crate::args::pngs_in_order;
+        use crate::icon_conversion::IconResolution;
+        use clap::{CommandFactory, FromArgMatches};
+
+        let pngs = |argv: &[&str]| {
+            let matches = FolderifyArgs::command()
+                .try_get_matches_from(["folderify", "mask.png"].iter().chain(argv))
+                .unwrap();
+            let args = FolderifyArgs::from_arg_matches(&matches).unwrap();
+            pngs_in_order(&matches, args.output_png, args.size)
+        };
+        assert_eq!(
+            pngs(&[
+                "--output-png",
+                "a.png",
+                "--output-png",
+                "b.png",
+                "--size",
+                "48",
+            ]),
+            Ok(vec![
+                ("a.png".into(), IconResolution::Retina512),
+                ("b.png".into(), IconResolution::Custom(48)),
+            ])
+        );
+        assert!(pngs(&["--size", "48", "--output-png", "a.png"]).is_err());
+        assert!(pngs(&["--output-png", "a.png", "--size", "48", "--size", "64"]).is_err());
+    }
+
+    #[test]
+    fn test_badges_in_order() {
+        use crate::args::{badges_in_order, BadgeSource};
+        use clap::{CommandFactory, FromArgMatches};
+
+        let matches = FolderifyArgs::command()
+            .try_get_matches_from([
+                "folderify",
+                "--badge-image",
+                "a.png",
+                "--badge",
+                "locked:top-right",
+                "--badge-image",
+                "b.png",
+                "mask.png",
+            ])
+            .unwrap();
+        let args = FolderifyArgs::from_arg_matches(&matches).unwrap();
+        let sources: Vec<BadgeSource> = badges_in_order(&matches, args.badge, args.badge_image)
+            .into_iter()
+            .map(|badge| badge.source)
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                BadgeSource::Image("a.png".into()),
+                BadgeSource::BuiltIn(crate::args::Badge::Locked),
+                BadgeSource::Image("b.png".into()),
+            ]
+        );
+    }
 }
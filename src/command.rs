@@ -7,11 +7,13 @@ use crate::error::CommandFailedError;
 use crate::error::CommandInvalidError;
 use crate::error::FolderifyError;
 use crate::error::GeneralError;
+use crate::interrupt::ChildProcess;
 use crate::magick::CommandArgs;
+use crate::status;
 
 const DEBUG_PRINT_ARGS: bool = false;
 
-const MAGICK_COMMAND: &str = "magick";
+pub(crate) const MAGICK_COMMAND: &str = "magick";
 const IDENTIFY_COMMAND: &str = "identify";
 pub(crate) const ICONUTIL_COMMAND: &str = "iconutil";
 pub(crate) const OPEN_COMMAND: &str = "open";
@@ -24,13 +26,15 @@ pub(crate) const DEREZ_COMMAND: &str = "DeRez";
 pub(crate) const REZ_COMMAND: &str = "Rez";
 pub(crate) const SETFILE_COMMAND: &str = "SetFile";
 
+pub(crate) const GIO_COMMAND: &str = "gio";
+
 pub(crate) fn run_command(
     command_name: &str,
     args: &CommandArgs,
     stdin: Option<&[u8]>,
 ) -> Result<Vec<u8>, FolderifyError> {
     if DEBUG_PRINT_ARGS {
-        println!("args: {}", args.args.join(" "));
+        status!("args: {}", args.args.join(" "));
     };
     let child = Command::new(command_name)
         .args(args.args.iter())
@@ -45,6 +49,7 @@ pub(crate) fn run_command(
             }));
         }
     };
+    let _child_process = ChildProcess::register(child.id());
 
     if let Some(stdin) = stdin {
         let child_stdin = child.stdin.as_mut().unwrap(); // TODO
@@ -82,25 +87,36 @@ pub(crate) fn run_magick(args: &CommandArgs, stdin: Option<&[u8]>) -> Result<(),
     Ok(())
 }
 
+/// Whether ImageMagick can read a format (e.g. `PANGO`, which depends on an optional library).
+pub(crate) fn magick_supports_format(format: &str) -> Result<bool, FolderifyError> {
+    let mut args = CommandArgs::new();
+    args.push("-list");
+    args.push("format");
+    let stdout = run_command(MAGICK_COMMAND, &args, None)?;
+    // e.g. `    PANGO* PANGO     r--   Pango Markup Language (Pango 1.50.14)`
+    Ok(String::from_utf8_lossy(&stdout).lines().any(|line| {
+        line.split_whitespace()
+            .next()
+            .is_some_and(|name| name.trim_end_matches(['*', '+', '-']) == format)
+    }))
+}
+
 pub(crate) fn identify_read_u32(args: &CommandArgs) -> Result<u32, FolderifyError> {
     let stdout = run_command(IDENTIFY_COMMAND, args, None)?;
     let s: &str = match from_utf8(&stdout) {
         Ok(s) => s,
-        Err(s) => {
-            println!("errerrerr{}+++++", s);
+        Err(e) => {
             return Err((GeneralError {
-                message: "Could not read input dimensions".into(),
+                message: format!("Could not read input dimensions: {}", e),
             })
             .into());
         }
     };
     let value = match s.parse::<u32>() {
         Ok(value) => value,
-        Err(s) => {
-            // TODO
-            println!("errerrerr{}+++++", s);
+        Err(e) => {
             return Err((GeneralError {
-                message: "Could not read input dimensions".into(),
+                message: format!("Could not parse `identify` output ({:?}): {}", s, e),
             })
             .into());
         }
@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use crate::{args::IconVariant, icon_conversion::IconResolution, magick::CommandArgs};
+
+/// Space around each icon, in pixels.
+const ICON_PADDING: u32 = 16;
+/// Space around each panel, in pixels.
+const PANEL_PADDING: u32 = 32;
+const SIZE_LABEL_POINT_SIZE: u32 = 18;
+const VARIANT_LABEL_POINT_SIZE: u32 = 28;
+
+/// Every variant is shown on both backgrounds, so that it can be checked in light and dark contexts.
+const PANELS: [Panel; 2] = [
+    Panel {
+        background: "#f5f5f5",
+        text: "#3c3c3c",
+    },
+    Panel {
+        background: "#1e1e1e",
+        text: "#dcdcdc",
+    },
+];
+
+struct Panel {
+    background: &'static str,
+    text: &'static str,
+}
+
+/// `magick` args that lay out every resolution of every variant (smallest first), with labels.
+/// The panels (one per background) are stacked vertically.
+pub fn contact_sheet_args(
+    variant_iconsets: &[(&IconVariant, &Path)],
+    output_path: &Path,
+) -> CommandArgs {
+    let mut args = CommandArgs::new();
+    for panel in &PANELS {
+        args.push("(");
+        for (variant, iconset_dir) in variant_iconsets {
+            push_label(
+                &mut args,
+                &format!("{}, {}", variant.folder_style, variant.color_scheme),
+                panel,
+                VARIANT_LABEL_POINT_SIZE,
+            );
+            args.push("(");
+            for resolution in IconResolution::values().iter().rev() {
+                args.push("(");
+                args.push_path(&iconset_dir.join(resolution.icon_file()));
+                push_label(
+                    &mut args,
+                    &resolution.to_string(),
+                    panel,
+                    SIZE_LABEL_POINT_SIZE,
+                );
+                push_append(&mut args, "center", "-append");
+                push_border(&mut args, ICON_PADDING);
+                args.push(")");
+            }
+            push_append(&mut args, "south", "+append");
+            args.push(")");
+        }
+        push_append(&mut args, "west", "-append");
+        push_border(&mut args, PANEL_PADDING);
+        args.push("-background");
+        args.push(panel.background);
+        args.flatten();
+        args.push(")");
+    }
+    push_append(&mut args, "west", "-append");
+    args.push_path(output_path);
+    args
+}
+
+fn push_label(args: &mut CommandArgs, text: &str, panel: &Panel, point_size: u32) {
+    args.push("(");
+    args.push("-background");
+    args.push("none");
+    args.push("-fill");
+    args.push(panel.text);
+    args.push("-pointsize");
+    args.push(&point_size.to_string());
+    args.push_string(format!("label:{}", text));
+    args.push(")");
+}
+
+fn push_append(args: &mut CommandArgs, gravity: &str, append: &str) {
+    args.push("-background");
+    args.push("none");
+    args.push("-gravity");
+    args.push(gravity);
+    args.push(append);
+}
+
+/// A transparent border.
+fn push_border(args: &mut CommandArgs, width: u32) {
+    args.push("-bordercolor");
+    args.push("none");
+    args.push("-border");
+    args.push(&width.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        args::{ColorScheme, FolderStyle, IconVariant},
+        contact_sheet::contact_sheet_args,
+    };
+
+    #[test]
+    fn test_contact_sheet_args() {
+        let light = IconVariant {
+            folder_style: FolderStyle::Tahoe,
+            color_scheme: ColorScheme::Light,
+            name_suffix: None,
+        };
+        let dark = IconVariant {
+            color_scheme: ColorScheme::Dark,
+            name_suffix: Some("dark".into()),
+            ..light.clone()
+        };
+        let args = contact_sheet_args(
+            &[
+                (&light, Path::new("a.iconset")),
+                (&dark, Path::new("b.iconset")),
+            ],
+            Path::new("sheet.png"),
+        )
+        .args;
+
+        let icons: Vec<&String> = args.iter().filter(|arg| arg.ends_with(".png")).collect();
+        // 2 variants × 10 resolutions × 2 backgrounds, and the output.
+        assert_eq!(icons.len(), 41);
+        assert_eq!(icons[0], "a.iconset/icon_16x16.png");
+        assert_eq!(icons[9], "a.iconset/icon_512x512@2x.png");
+        assert_eq!(icons[10], "b.iconset/icon_16x16.png");
+        assert_eq!(icons[40], "sheet.png");
+        assert!(args.contains(&"label:Tahoe, dark".to_owned()));
+        assert_eq!(
+            args.iter().filter(|arg| *arg == "(").count(),
+            args.iter().filter(|arg| *arg == ")").count()
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use std::{
+    collections::HashMap,
+    io::{stdin, stdout, BufRead, Write},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    args::{try_options_from, Options, STDIO_PATH},
+    error::FolderifyError,
+    icon_conversion::{ProgressEvent, RenderMonitor},
+    json_flags::{flags_from_json, has_text_mask},
+    render::render,
+    status::{set_status_destination, StatusDestination},
+};
+
+const JSONRPC_VERSION: &str = "2.0";
+
+// https://www.jsonrpc.org/specification#error_object
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const RENDER_FAILED: i64 = -32000;
+const RENDER_CANCELLED: i64 = -32001;
+
+/// Flags that make no sense for a single render, or that would write to stdout.
+const UNSUPPORTED_OPTION_FLAGS: &[&str] = &["completions", "help", "version", "preview", "jobs"];
+
+#[derive(Debug, PartialEq)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: &str) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    fn invalid_params(message: &str) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+}
+
+impl From<FolderifyError> for RpcError {
+    fn from(value: FolderifyError) -> Self {
+        let mut rpc_error = RpcError::new(RENDER_FAILED, &value.to_string());
+        rpc_error.data = match value {
+            FolderifyError::CommandInvalid(e) => Some(json!({
+                "type": "command_invalid",
+                "command": e.command_name,
+            })),
+            FolderifyError::CommandFailed(e) => Some(json!({
+                "type": "command_failed",
+                "command": e.command_name,
+                "stderr": String::from_utf8_lossy(&e.stderr),
+            })),
+            FolderifyError::General(_) => Some(json!({ "type": "general" })),
+        };
+        rpc_error
+    }
+}
+
+#[derive(Default)]
+struct Daemon {
+    /// Monitors for the running renders (for cancellation), by request id.
+    running: Mutex<HashMap<String, RenderMonitor>>,
+}
+
+/// Handles requests until stdin is closed, then waits for running renders to finish.
+///
+/// Folder templates are embedded in the binary, so a long-running daemon only has to run the
+/// conversion commands for each request.
+pub fn daemon() {
+    set_status_destination(StatusDestination::None);
+
+    let daemon = Arc::new(Daemon::default());
+    let mut handles = Vec::<JoinHandle<()>>::new();
+    for line in stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(handle) = daemon.clone().handle_line(&line) {
+            handles.push(handle);
+        }
+    }
+    for handle in handles {
+        // Panicking renders are caught (and reported) by their thread.
+        let _ = handle.join();
+    }
+}
+
+impl Daemon {
+    fn send(&self, message: &Value) {
+        // Responses and notifications are written one per line, so they must not interleave.
+        let mut stdout = stdout().lock();
+        // If stdout is closed, there is no one left to tell.
+        let _ = writeln!(stdout, "{}", message);
+        let _ = stdout.flush();
+    }
+
+    fn respond(&self, id: Option<&Value>, result: Result<Value, RpcError>) {
+        // Requests without an `id` are notifications, which do not get a response.
+        let Some(id) = id else {
+            return;
+        };
+        self.send(&match result {
+            Ok(result) => json!({ "jsonrpc": JSONRPC_VERSION, "id": id, "result": result }),
+            Err(e) => {
+                let mut error = json!({ "code": e.code, "message": e.message });
+                if let Some(data) = e.data {
+                    error["data"] = data;
+                }
+                json!({ "jsonrpc": JSONRPC_VERSION, "id": id, "error": error })
+            }
+        });
+    }
+
+    /// Returns a handle if the request started a render in the background.
+    fn handle_line(self: Arc<Self>, line: &str) -> Option<JoinHandle<()>> {
+        let request = match serde_json::from_str::<Value>(line) {
+            Ok(request) => request,
+            Err(e) => {
+                self.respond(
+                    Some(&Value::Null),
+                    Err(RpcError::new(PARSE_ERROR, &format!("Invalid JSON: {}", e))),
+                );
+                return None;
+            }
+        };
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            self.respond(
+                Some(id.as_ref().unwrap_or(&Value::Null)),
+                Err(RpcError::new(
+                    INVALID_REQUEST,
+                    "A request must have a `method`.",
+                )),
+            );
+            return None;
+        };
+        let params = request.get("params").cloned().unwrap_or(json!({}));
+        let Some(params) = params.as_object() else {
+            self.respond(
+                id.as_ref(),
+                Err(RpcError::invalid_params("`params` must be an object.")),
+            );
+            return None;
+        };
+
+        match method {
+            "render" => self.start_render(id, params, false),
+            "assign" => self.start_render(id, params, true),
+            "inspect" => {
+                self.respond(id.as_ref(), inspect(params));
+                None
+            }
+            "cancel" => {
+                self.respond(id.as_ref(), self.cancel(params));
+                None
+            }
+            _ => {
+                self.respond(
+                    id.as_ref(),
+                    Err(RpcError::new(
+                        METHOD_NOT_FOUND,
+                        &format!("Unknown method: {}", method),
+                    )),
+                );
+                None
+            }
+        }
+    }
+
+    fn start_render(
+        self: Arc<Self>,
+        id: Option<Value>,
+        params: &Map<String, Value>,
+        assign: bool,
+    ) -> Option<JoinHandle<()>> {
+        let options = match options_from_params(params, assign) {
+            Ok(options) => options,
+            Err(e) => {
+                self.respond(id.as_ref(), Err(e));
+                return None;
+            }
+        };
+
+        let running_key = id.as_ref().map(Value::to_string);
+        let mut monitor = RenderMonitor::default();
+        let progress_daemon = self.clone();
+        let progress_id = id.clone().unwrap_or(Value::Null);
+        monitor.progress_listener = Some(Arc::new(move |event: ProgressEvent| {
+            progress_daemon.send(&json!({
+                "jsonrpc": JSONRPC_VERSION,
+                "method": "progress",
+                "params": {
+                    "id": progress_id,
+                    "type": event.progress_bar_type.name(),
+                    "stage": event.stage,
+                    "step": event.step,
+                    "position": event.position,
+                    "length": event.length,
+                },
+            }));
+        }));
+        if let Some(running_key) = &running_key {
+            let mut running = self.running.lock().unwrap();
+            if running.contains_key(running_key) {
+                drop(running);
+                self.respond(
+                    id.as_ref(),
+                    Err(RpcError::new(
+                        INVALID_REQUEST,
+                        "A request with this `id` is already running.",
+                    )),
+                );
+                return None;
+            }
+            running.insert(running_key.clone(), monitor.clone());
+        }
+
+        Some(thread::spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| render(&options, &monitor)));
+            if let Some(running_key) = &running_key {
+                self.running.lock().unwrap().remove(running_key);
+            }
+            let result = match result {
+                Ok(Ok(())) => Ok(Value::Null),
+                Ok(Err(_)) if monitor.is_cancelled() => {
+                    Err(RpcError::new(RENDER_CANCELLED, "The render was cancelled."))
+                }
+                Ok(Err(e)) => Err(e.into()),
+                Err(_) => Err(RpcError::new(RENDER_FAILED, "The render panicked.")),
+            };
+            self.respond(id.as_ref(), result);
+        }))
+    }
+
+    fn cancel(&self, params: &Map<String, Value>) -> Result<Value, RpcError> {
+        let Some(id) = params.get("id") else {
+            return Err(RpcError::invalid_params(
+                "`id` (of the request to cancel) is required.",
+            ));
+        };
+        let cancelled = match self.running.lock().unwrap().get(&id.to_string()) {
+            Some(monitor) => {
+                monitor.cancel();
+                true
+            }
+            None => false,
+        };
+        Ok(json!({ "cancelled": cancelled }))
+    }
+}
+
+fn string_param<'a>(
+    params: &'a Map<String, Value>,
+    name: &str,
+) -> Result<Option<&'a str>, RpcError> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(RpcError::invalid_params(&format!(
+            "`{}` must be a string.",
+            name
+        ))),
+    }
+}
+
+/// Converts `render`/`assign`/`inspect` params (`mask`, `target`, and `options`) to command-line
+/// arguments.
+fn args_from_params(params: &Map<String, Value>, assign: bool) -> Result<Vec<String>, RpcError> {
+    for name in params.keys() {
+        if !["mask", "target", "options"].contains(&name.as_str()) {
+            return Err(RpcError::invalid_params(&format!(
+                "Unknown parameter: {}",
+                name
+            )));
+        }
+    }
+    let mask = string_param(params, "mask")?;
+    let target = string_param(params, "target")?;
+    match (assign, target) {
+        (true, None) => return Err(RpcError::invalid_params("`target` is required.")),
+        (false, Some(_)) => {
+            return Err(RpcError::invalid_params(
+                "`target` is only supported for `assign`.",
+            ))
+        }
+        _ => {}
+    }
+    if target.is_some_and(|target| !Path::new(target).exists()) {
+        return Err(RpcError::invalid_params("`target` does not exist."));
+    }
+    if mask == Some(STDIO_PATH) {
+        return Err(RpcError::invalid_params(
+            "Reading the mask from stdin is not supported.",
+        ));
+    }
+    let flags = match params.get("options") {
+        None => vec![],
+        Some(Value::Object(fields)) => {
+            flags_from_json(fields, |name| !UNSUPPORTED_OPTION_FLAGS.contains(&name))
+                .map_err(|message| RpcError::invalid_params(&message))?
+        }
+        Some(_) => return Err(RpcError::invalid_params("`options` must be an object.")),
+    };
+    if mask.is_some() && has_text_mask(&flags) {
+        return Err(RpcError::invalid_params(
+            "`mask` cannot be combined with `text` or `emoji`.",
+        ));
+    }
+
+    let mut args = vec!["folderify".to_owned()];
+    args.extend(flags);
+    args.push("--no-progress".into());
+    args.push("--".into());
+    args.extend(mask.map(String::from));
+    args.extend(target.map(String::from));
+    Ok(args)
+}
+
+fn options_from_params(params: &Map<String, Value>, assign: bool) -> Result<Options, RpcError> {
+    let args = args_from_params(params, assign)?;
+    let options =
+        try_options_from(args).map_err(|message| RpcError::invalid_params(message.trim()))?;
+    if options.icns_to_stdout() {
+        return Err(RpcError::invalid_params(
+            "Writing the `.icns` file to stdout is not supported.",
+        ));
+    }
+    Ok(options)
+}
+
+/// The command-line name of a value, e.g. `big-sur`.
+fn value_name(value: impl ValueEnum) -> Value {
+    value
+        .to_possible_value()
+        .map(|possible_value| possible_value.get_name().into())
+        .unwrap_or_default()
+}
+
+/// Reports what a `render` (or `assign`, if there is a `target`) would do, without rendering.
+fn inspect(params: &Map<String, Value>) -> Result<Value, RpcError> {
+    let assign = params.contains_key("target");
+    let options = options_from_params(params, assign)?;
+    let path_json = |path: &Path| Value::from(path.to_string_lossy());
+    Ok(json!({
+        "mask": path_json(&options.mask_path),
+        "target": options.target.as_deref().map(path_json),
+        "folder_style": value_name(options.folder_style),
+        "color_scheme": options.color_scheme.to_string(),
+        "variants": options
+            .variants()
+            .iter()
+            .map(|variant| json!({
+                "folder_style": value_name(variant.folder_style),
+                "color_scheme": variant.color_scheme.to_string(),
+                "name_suffix": variant.name_suffix,
+            }))
+            .collect::<Vec<Value>>(),
+        "mask_overrides": options
+            .mask_overrides
+            .iter()
+            .map(|mask_override| json!({
+                "size": mask_override.resolution.to_string(),
+                "path": path_json(&mask_override.path),
+            }))
+            .collect::<Vec<Value>>(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::daemon::{args_from_params, RpcError, INVALID_PARAMS};
+
+    #[test]
+    fn test_args_from_params() {
+        let params = json!({
+            "mask": "logo.png",
+            "target": "src",
+            "options": {"folder-style": "tahoe", "badge": ["locked"], "no-trim": true},
+        });
+        assert_eq!(
+            args_from_params(params.as_object().unwrap(), true),
+            Ok(vec![
+                "folderify".into(),
+                "--badge=locked".into(),
+                "--folder-style=tahoe".into(),
+                "--no-trim".into(),
+                "--no-progress".into(),
+                "--".into(),
+                "logo.png".into(),
+                "src".into(),
+            ])
+        );
+        assert_eq!(
+            args_from_params(params.as_object().unwrap(), false),
+            Err(RpcError::invalid_params(
+                "`target` is only supported for `assign`."
+            ))
+        );
+        let missing_target = json!({"mask": "logo.png", "target": "missing-target"});
+        assert_eq!(
+            args_from_params(missing_target.as_object().unwrap(), true),
+            Err(RpcError::invalid_params("`target` does not exist."))
+        );
+        let stdin_mask = json!({"mask": "-"});
+        assert_eq!(
+            args_from_params(stdin_mask.as_object().unwrap(), false)
+                .unwrap_err()
+                .code,
+            INVALID_PARAMS
+        );
+        let text_and_mask = json!({"mask": "logo.png", "options": {"text": "API"}});
+        assert_eq!(
+            args_from_params(text_and_mask.as_object().unwrap(), false),
+            Err(RpcError::invalid_params(
+                "`mask` cannot be combined with `text` or `emoji`."
+            ))
+        );
+        let unknown_param = json!({"mask": "logo.png", "size": 16});
+        assert!(args_from_params(unknown_param.as_object().unwrap(), false).is_err());
+        let unsupported_option = json!({"options": {"completions": "zsh"}});
+        assert!(args_from_params(unsupported_option.as_object().unwrap(), false).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 #[derive(Debug)]
 #[allow(dead_code)] // For debugging
 pub enum FolderifyError {
@@ -6,6 +8,18 @@ pub enum FolderifyError {
     General(GeneralError),
 }
 
+impl Display for FolderifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FolderifyError::CommandInvalid(e) => {
+                write!(f, "Could not run `{}`. Is it installed?", e.command_name)
+            }
+            FolderifyError::CommandFailed(e) => write!(f, "`{}` failed.", e.command_name),
+            FolderifyError::General(e) => write!(f, "{}", e.message),
+        }
+    }
+}
+
 #[derive(Debug)]
 #[allow(dead_code)] // For debugging
 pub struct CommandInvalidError {
@@ -0,0 +1,288 @@
+use crate::icon_conversion::IconResolution;
+
+const ICNS_MAGIC: &[u8; 4] = b"icns";
+const TOC_TYPE: &[u8; 4] = b"TOC ";
+/// Contains a complete `.icns` file with the icons for dark mode.
+const DARK_VARIANT_TYPE: &[u8; 4] = b"\xfd\xd9\x2f\xa8";
+/// Chunk type and length.
+const CHUNK_HEADER_LENGTH: usize = 8;
+
+/// Runs of identical bytes are only encoded if they are at least this long.
+const RLE_MIN_RUN: usize = 3;
+const RLE_MAX_RUN: usize = 130;
+const RLE_MAX_LITERAL: usize = 128;
+
+/// Pre-PNG icon formats (24-bit RGB with a separate 8-bit mask), which are still read by some
+/// older tools.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LegacyIcon {
+    Small,
+    Large,
+    Thumbnail,
+}
+
+impl LegacyIcon {
+    pub fn values() -> [LegacyIcon; 3] {
+        [LegacyIcon::Small, LegacyIcon::Large, LegacyIcon::Thumbnail]
+    }
+
+    pub fn resolution(&self) -> IconResolution {
+        match self {
+            LegacyIcon::Small => IconResolution::NonRetina16,
+            LegacyIcon::Large => IconResolution::NonRetina32,
+            LegacyIcon::Thumbnail => IconResolution::NonRetina128,
+        }
+    }
+
+    fn rgb_type(&self) -> &'static [u8; 4] {
+        match self {
+            LegacyIcon::Small => b"is32",
+            LegacyIcon::Large => b"il32",
+            LegacyIcon::Thumbnail => b"it32",
+        }
+    }
+
+    fn mask_type(&self) -> &'static [u8; 4] {
+        match self {
+            LegacyIcon::Small => b"s8mk",
+            LegacyIcon::Large => b"l8mk",
+            LegacyIcon::Thumbnail => b"t8mk",
+        }
+    }
+}
+
+/// The RLE variant used by `.icns` files (similar to PackBits): a header byte below `0x80` is
+/// followed by that many plus one literal bytes, and a header byte `0x80` or above repeats the
+/// following byte (header - `0x80` + 3) times.
+pub fn rle(data: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::<u8>::new();
+    let mut literal = Vec::<u8>::new();
+    let flush_literal = |encoded: &mut Vec<u8>, literal: &mut Vec<u8>| {
+        if !literal.is_empty() {
+            encoded.push((literal.len() - 1) as u8);
+            encoded.append(literal);
+        }
+    };
+    let mut i = 0;
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(RLE_MAX_RUN)
+            .take_while(|byte| **byte == data[i])
+            .count();
+        if run >= RLE_MIN_RUN {
+            flush_literal(&mut encoded, &mut literal);
+            encoded.push((0x80 + run - RLE_MIN_RUN) as u8);
+            encoded.push(data[i]);
+            i += run;
+        } else {
+            literal.push(data[i]);
+            if literal.len() == RLE_MAX_LITERAL {
+                flush_literal(&mut encoded, &mut literal);
+            }
+            i += 1;
+        }
+    }
+    flush_literal(&mut encoded, &mut literal);
+    encoded
+}
+
+fn new_chunk<'a>((chunk_type, data): &'a (&[u8; 4], Vec<u8>)) -> (&'a [u8], &'a [u8]) {
+    (chunk_type.as_slice(), data.as_slice())
+}
+
+fn push_chunk(icns: &mut Vec<u8>, chunk_type: &[u8], data: &[u8]) {
+    icns.extend(chunk_type);
+    icns.extend(((CHUNK_HEADER_LENGTH + data.len()) as u32).to_be_bytes());
+    icns.extend(data);
+}
+
+/// The RGB and mask chunks for an icon, from 8-bit RGBA pixels.
+pub fn legacy_chunks(legacy_icon: LegacyIcon, rgba: &[u8]) -> Vec<(&'static [u8; 4], Vec<u8>)> {
+    let channel =
+        |offset: usize| -> Vec<u8> { rgba.iter().skip(offset).step_by(4).copied().collect() };
+    let mut rgb = Vec::<u8>::new();
+    if legacy_icon == LegacyIcon::Thumbnail {
+        // `it32` data starts with 4 (unused) zero bytes.
+        rgb.extend([0; 4]);
+    }
+    for offset in 0..3 {
+        rgb.extend(rle(&channel(offset)));
+    }
+    vec![
+        (legacy_icon.rgb_type(), rgb),
+        (legacy_icon.mask_type(), channel(3)),
+    ]
+}
+
+/// Adds legacy chunks to an `.icns` file (e.g. as written by `iconutil`).
+pub fn with_legacy_chunks(icns: &[u8], chunks: &[(&[u8; 4], Vec<u8>)]) -> Result<Vec<u8>, String> {
+    // Older readers expect the legacy chunks first.
+    with_chunks(icns, chunks, &[])
+}
+
+/// Embeds `dark_icns` in `icns`, for dark mode.
+pub fn with_dark_variant(icns: &[u8], dark_icns: &[u8]) -> Result<Vec<u8>, String> {
+    if dark_icns.get(0..4) != Some(ICNS_MAGIC) {
+        return Err("The dark `.icns` file is invalid.".into());
+    }
+    with_chunks(icns, &[], &[(DARK_VARIANT_TYPE, dark_icns.to_vec())])
+}
+
+/// Adds chunks before and after the existing chunks of an `.icns` file, replacing chunks of the
+/// same type and updating the table of contents (if there is one).
+fn with_chunks(
+    icns: &[u8],
+    leading_chunks: &[(&[u8; 4], Vec<u8>)],
+    trailing_chunks: &[(&[u8; 4], Vec<u8>)],
+) -> Result<Vec<u8>, String> {
+    let invalid = || "The `.icns` file is invalid.".to_owned();
+    if icns.get(0..4) != Some(ICNS_MAGIC) {
+        return Err(invalid());
+    }
+    let mut existing_chunks = Vec::<(&[u8], &[u8])>::new();
+    let mut has_toc = false;
+    let mut rest = icns.get(CHUNK_HEADER_LENGTH..).ok_or_else(invalid)?;
+    while !rest.is_empty() {
+        let chunk_type = rest.get(0..4).ok_or_else(invalid)?;
+        let length = rest
+            .get(4..8)
+            .map(|length| u32::from_be_bytes(length.try_into().unwrap()) as usize)
+            .filter(|length| *length >= CHUNK_HEADER_LENGTH)
+            .ok_or_else(invalid)?;
+        let data = rest.get(CHUNK_HEADER_LENGTH..length).ok_or_else(invalid)?;
+        match chunk_type == TOC_TYPE {
+            true => has_toc = true,
+            false => {
+                if !leading_chunks
+                    .iter()
+                    .chain(trailing_chunks)
+                    .any(|(new_type, _)| *new_type == chunk_type)
+                {
+                    existing_chunks.push((chunk_type, data));
+                }
+            }
+        }
+        rest = &rest[length..];
+    }
+
+    let all_chunks: Vec<(&[u8], &[u8])> = leading_chunks
+        .iter()
+        .map(new_chunk)
+        .chain(existing_chunks)
+        .chain(trailing_chunks.iter().map(new_chunk))
+        .collect();
+    let mut body = Vec::<u8>::new();
+    if has_toc {
+        let mut toc = Vec::<u8>::new();
+        for (chunk_type, data) in &all_chunks {
+            toc.extend(*chunk_type);
+            toc.extend(((CHUNK_HEADER_LENGTH + data.len()) as u32).to_be_bytes());
+        }
+        push_chunk(&mut body, TOC_TYPE, &toc);
+    }
+    for (chunk_type, data) in &all_chunks {
+        push_chunk(&mut body, chunk_type, data);
+    }
+
+    let mut output = ICNS_MAGIC.to_vec();
+    output.extend(((CHUNK_HEADER_LENGTH + body.len()) as u32).to_be_bytes());
+    output.extend(body);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::icns::{
+        legacy_chunks, push_chunk, rle, with_dark_variant, with_legacy_chunks, LegacyIcon,
+    };
+
+    #[test]
+    fn test_rle() {
+        assert_eq!(rle(&[]), [] as [u8; 0]);
+        assert_eq!(rle(&[1, 2, 3]), [0x02, 1, 2, 3]);
+        assert_eq!(rle(&[5, 5, 5]), [0x80, 5]);
+        // Runs of 2 are cheaper as literals.
+        assert_eq!(
+            rle(&[1, 2, 2, 3, 3, 3, 3, 4]),
+            [0x02, 1, 2, 2, 0x81, 3, 0x00, 4]
+        );
+        assert_eq!(rle(&[7; 130]), [0xff, 7]);
+        assert_eq!(rle(&[7; 131]), [0xff, 7, 0x00, 7]);
+        assert_eq!(rle(&[7; 133]), [0xff, 7, 0x80, 7]);
+
+        let literal: Vec<u8> = (0..=255).cycle().take(129).collect();
+        let encoded = rle(&literal);
+        assert_eq!(encoded.len(), 1 + 128 + 1 + 1);
+        assert_eq!(encoded[0], 0x7f);
+        assert_eq!(&encoded[1..129], &literal[0..128]);
+        assert_eq!(&encoded[129..], [0x00, 128]);
+    }
+
+    #[test]
+    fn test_legacy_chunks() {
+        // A 16x16 icon in a single color, with a transparent first pixel.
+        let mut rgba = [0x10, 0x20, 0x30, 0xff].repeat(256);
+        rgba[3] = 0;
+        let chunks = legacy_chunks(LegacyIcon::Small, &rgba);
+        assert_eq!(chunks[0].0, b"is32");
+        assert_eq!(
+            chunks[0].1,
+            [0xff, 0x10, 0xfb, 0x10, 0xff, 0x20, 0xfb, 0x20, 0xff, 0x30, 0xfb, 0x30]
+        );
+        assert_eq!(chunks[1].0, b"s8mk");
+        assert_eq!(chunks[1].1.len(), 256);
+        assert_eq!(&chunks[1].1[0..2], [0x00, 0xff]);
+
+        let chunks = legacy_chunks(LegacyIcon::Thumbnail, &[0; 128 * 128 * 4]);
+        assert_eq!(chunks[0].0, b"it32");
+        assert_eq!(&chunks[0].1[0..6], [0, 0, 0, 0, 0xff, 0]);
+        assert_eq!(chunks[1].0, b"t8mk");
+    }
+
+    #[test]
+    fn test_with_legacy_chunks() {
+        let mut body = Vec::<u8>::new();
+        push_chunk(&mut body, b"TOC ", b"ic10\0\0\0\x0bis32\0\0\0\x09");
+        push_chunk(&mut body, b"ic10", b"png");
+        push_chunk(&mut body, b"is32", b"x");
+        let mut icns = b"icns".to_vec();
+        icns.extend(((8 + body.len()) as u32).to_be_bytes());
+        icns.extend(body);
+
+        assert_eq!(
+            with_legacy_chunks(
+                &icns,
+                &[(b"is32", b"rgb".to_vec()), (b"s8mk", b"m".to_vec())]
+            )
+            .unwrap(),
+            [
+                b"icns\0\0\0\x47" as &[u8],
+                b"TOC \0\0\0\x20is32\0\0\0\x0bs8mk\0\0\0\x09ic10\0\0\0\x0b",
+                b"is32\0\0\0\x0brgb",
+                b"s8mk\0\0\0\x09m",
+                b"ic10\0\0\0\x0bpng",
+            ]
+            .concat()
+        );
+        assert!(with_legacy_chunks(b"nope\0\0\0\x08", &[]).is_err());
+        assert!(with_legacy_chunks(b"icns\0\0\0\x10ic10\0\0\0\x20", &[]).is_err());
+    }
+
+    #[test]
+    fn test_with_dark_variant() {
+        let light = b"icns\0\0\0\x13ic10\0\0\0\x0bpng";
+        let dark = b"icns\0\0\0\x13ic10\0\0\0\x0bdrk";
+        assert_eq!(
+            with_dark_variant(light, dark).unwrap(),
+            [
+                b"icns\0\0\0\x2e" as &[u8],
+                b"ic10\0\0\0\x0bpng",
+                b"\xfd\xd9\x2f\xa8\0\0\0\x1b",
+                dark,
+            ]
+            .concat()
+        );
+        assert!(with_dark_variant(light, b"png").is_err());
+    }
+}
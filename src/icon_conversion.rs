@@ -1,15 +1,26 @@
 use std::{
+    borrow::Cow,
+    cell::Cell,
+    cmp::min,
     fmt::Display,
     fs::{self, create_dir_all, metadata},
+    io::{stdin, Read},
+    ops::RangeInclusive,
     path::{Path, PathBuf},
-    process::exit,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use indicatif::{MultiProgress, ProgressBar, ProgressFinish, ProgressStyle};
-use mktemp::Temp;
 
 const RETINA_SCALE: u32 = 2;
+/// Custom sizes are interpolated between the `.iconset` resolutions, so they can't be smaller or
+/// larger than those.
+pub const CUSTOM_SIZE_RANGE: RangeInclusive<u32> = 16..=1024;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ProgressBarType {
     Input,
     Conversion,
@@ -21,35 +32,113 @@ impl ProgressBarType {
     pub fn num_steps(&self, options: &Options) -> u64 {
         match self {
             ProgressBarType::Input => 1,
-            ProgressBarType::Conversion => 13 + if options.badge.is_some() { 1 } else { 0 },
+            ProgressBarType::Conversion => {
+                let num_drawing_steps = match (options.mode, options.shadow) {
+                    (RenderMode::Engrave, _) => 13,
+                    (RenderMode::Color, true) => 5,
+                    (RenderMode::Color, false) => 2,
+                };
+                num_drawing_steps + options.badges.len() as u64
+            }
             ProgressBarType::OutputWithAssignment => {
                 2 + if matches!(options.set_icon_using, SetIconUsing::Rez) {
                     7
                 } else {
                     0
-                }
+                } + Self::num_additional_output_steps(options)
             }
-            ProgressBarType::OutputIcns => 1,
+            ProgressBarType::OutputIcns => 1 + Self::num_additional_output_steps(options),
         }
     }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProgressBarType::Input => "input",
+            ProgressBarType::Conversion => "conversion",
+            ProgressBarType::OutputWithAssignment | ProgressBarType::OutputIcns => "output",
+        }
+    }
+
+    fn num_additional_output_steps(options: &Options) -> u64 {
+        [
+            options.output_icon_theme.is_some(),
+            options.output_xcassets.is_some(),
+            options.output_preview.is_some(),
+            options.output_animation.is_some(),
+            !options.output_pngs.is_empty(),
+        ]
+        .into_iter()
+        .filter(|output| *output)
+        .count() as u64
+    }
 }
 
 use crate::{
-    args::{Badge, ColorScheme, FolderStyle, Options, SetIconUsing},
+    animation::{self, AnimationFormat},
+    args::{
+        BadgePosition, BadgeSource, BadgeSpec, ColorScheme, FolderStyle, IconVariant, Options,
+        RenderMode, SetIconUsing,
+    },
     command::{
-        run_command, run_magick, DEREZ_COMMAND, FILEICON_COMMAND, ICONUTIL_COMMAND,
-        OSASCRIPT_COMMAND, REZ_COMMAND, SETFILE_COMMAND, SIPS_COMMAND,
+        magick_supports_format, run_command, run_magick, DEREZ_COMMAND, FILEICON_COMMAND,
+        GIO_COMMAND, ICONUTIL_COMMAND, MAGICK_COMMAND, OSASCRIPT_COMMAND, REZ_COMMAND,
+        SETFILE_COMMAND, SIPS_COMMAND,
     },
+    contact_sheet,
     error::{FolderifyError, GeneralError},
-    magick::{density, BlurDown, CommandArgs, CompositingOperation},
+    icns::{self, LegacyIcon},
+    icon_theme,
+    interrupt::TempDir,
+    magick::{density, BlurDown, CommandArgs, CompositingOperation, Gravity},
+    output_paths::with_name_suffix,
     primitives::{Dimensions, Extent, Offset, RGBColor},
     resources::{get_badge_icon, get_folder_icon, IconInputs},
+    status, xcassets,
 };
 
+/// A progress bar update, for following a render without a terminal (e.g. `folderify daemon`).
+pub struct ProgressEvent {
+    pub progress_bar_type: ProgressBarType,
+    pub stage: String,
+    pub step: String,
+    pub position: u64,
+    pub length: u64,
+}
+
+pub type ProgressListener = Arc<dyn Fn(ProgressEvent) + Send + Sync>;
+
+/// Lets the caller of a render follow its progress and cancel it.
+#[derive(Clone, Default)]
+pub struct RenderMonitor {
+    pub progress_listener: Option<ProgressListener>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl RenderMonitor {
+    /// The render stops with an error at its next step.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn check_cancelled(&self) -> Result<(), FolderifyError> {
+        if self.is_cancelled() {
+            return Err(FolderifyError::General(GeneralError {
+                message: "The render was cancelled.".into(),
+            }));
+        }
+        Ok(())
+    }
+}
+
 pub struct ScaledMaskInputs {
     pub icon_size: u32,
     pub mask_dimensions: Dimensions,
-    pub offset_y: i32,
+    pub mask_gravity: Gravity,
+    pub offset: Offset,
 }
 
 pub struct BezelInputs {
@@ -59,6 +148,11 @@ pub struct BezelInputs {
     pub opacity: f32,
 }
 
+pub struct ShadowInputs {
+    pub blur: BlurDown,
+    pub opacity: f32,
+}
+
 pub struct EngravingInputs {
     pub fill_color: RGBColor,
     pub top_bezel: BezelInputs,
@@ -67,13 +161,13 @@ pub struct EngravingInputs {
 
 #[derive(Debug)]
 pub struct WorkingDir {
-    working_dir: Temp,
+    working_dir: TempDir,
 }
 
 impl WorkingDir {
     pub fn new() -> Self {
         Self {
-            working_dir: Temp::new_dir().expect("Couldn't create a temp dir."),
+            working_dir: TempDir::new().expect("Couldn't create a temp dir."),
         }
     }
 
@@ -82,11 +176,13 @@ impl WorkingDir {
         progress_bar_type: ProgressBarType,
         stage_description: &str,
         multi_progress_bar: Option<MultiProgress>,
+        monitor: &RenderMonitor,
         options: &Options,
     ) -> IconConversion {
+        let num_steps = progress_bar_type.num_steps(options);
         let progress_bar = match multi_progress_bar {
             Some(multi_progress_bar) => {
-                let progress_bar = ProgressBar::new(progress_bar_type.num_steps(options));
+                let progress_bar = ProgressBar::new(num_steps);
                 let progress_bar = match progress_bar_type {
                     ProgressBarType::Conversion => multi_progress_bar.insert(1, progress_bar),
                     _ => multi_progress_bar.insert_from_back(0, progress_bar),
@@ -105,37 +201,57 @@ impl WorkingDir {
             None => None,
         };
         IconConversion {
-            working_dir: self.working_dir.as_path().to_owned(),
+            working_dir: self.working_dir.path().to_owned(),
             resolution_prefix: stage_description.into(),
             progress_bar,
+            progress_bar_type,
+            num_steps,
+            position: Cell::new(0),
+            monitor: monitor.clone(),
         }
     }
 
     pub fn open_in_finder(&self) -> Result<(), FolderifyError> {
         let mut open_args = CommandArgs::new();
-        open_args.push_path(&self.working_dir);
+        open_args.push_path(self.working_dir.path());
         run_command("open", &open_args, None)?;
         Ok(())
     }
 
+    /// Keeps the working dir after the render (for debugging).
     pub fn release(self) {
-        self.working_dir.release(); // TODO
+        self.working_dir.keep();
     }
 
     pub fn icon_file_with_extension(&self, extension: &str) -> PathBuf {
         self.working_dir
-            .as_path()
+            .path()
             .join("icon")
             .with_extension(extension)
     }
 
-    pub fn create_iconset_dir(&self, options: &Options) -> Result<PathBuf, FolderifyError> {
-        let iconset_dir = self.icon_file_with_extension("iconset");
+    /// Where a size that is not part of an `.iconset` is rendered (for `--output-png`).
+    pub fn custom_size_path(&self, variant: &IconVariant, resolution: &IconResolution) -> PathBuf {
+        with_name_suffix(
+            &self.working_dir.path().join(resolution.icon_file()),
+            &variant.name_suffix,
+        )
+    }
+
+    pub fn create_iconset_dir(
+        &self,
+        options: &Options,
+        variant: &IconVariant,
+    ) -> Result<PathBuf, FolderifyError> {
+        let iconset_dir = with_name_suffix(
+            &self.icon_file_with_extension("iconset"),
+            &variant.name_suffix,
+        );
         if options.verbose {
-            println!("[Iconset] {}", iconset_dir.display());
+            status!("[Iconset] {}", iconset_dir.display());
         };
         if let Err(e) = create_dir_all(&iconset_dir) {
-            println!("Error: {}", (e));
+            status!("Error: {}", (e));
             return Err(FolderifyError::General(GeneralError {
                 message: "Could not create iconset dir".into(),
             }));
@@ -144,6 +260,7 @@ impl WorkingDir {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum IconResolution {
     NonRetina16,
     Retina16,
@@ -155,6 +272,9 @@ pub enum IconResolution {
     Retina256,
     NonRetina512,
     Retina512,
+    /// A size (in pixels) that is not part of an `.iconset`, e.g. 48. Its drawing parameters are
+    /// interpolated from the `.iconset` resolutions.
+    Custom(u32),
 }
 
 impl IconResolution {
@@ -186,6 +306,35 @@ impl IconResolution {
             IconResolution::Retina256 => 256 * RETINA_SCALE,
             IconResolution::NonRetina512 => 512,
             IconResolution::Retina512 => 512 * RETINA_SCALE,
+            IconResolution::Custom(size) => *size,
+        }
+    }
+
+    /// Size in points, i.e. not accounting for the `scale()`.
+    pub fn base_size(&self) -> u32 {
+        match self {
+            IconResolution::NonRetina16 | IconResolution::Retina16 => 16,
+            IconResolution::NonRetina32 | IconResolution::Retina32 => 32,
+            IconResolution::NonRetina128 | IconResolution::Retina128 => 128,
+            IconResolution::NonRetina256 | IconResolution::Retina256 => 256,
+            IconResolution::NonRetina512 | IconResolution::Retina512 => 512,
+            IconResolution::Custom(size) => *size,
+        }
+    }
+
+    pub fn scale(&self) -> u32 {
+        match self {
+            IconResolution::NonRetina16
+            | IconResolution::NonRetina32
+            | IconResolution::NonRetina128
+            | IconResolution::NonRetina256
+            | IconResolution::NonRetina512
+            | IconResolution::Custom(_) => 1,
+            IconResolution::Retina16
+            | IconResolution::Retina32
+            | IconResolution::Retina128
+            | IconResolution::Retina256
+            | IconResolution::Retina512 => RETINA_SCALE,
         }
     }
 
@@ -201,6 +350,9 @@ impl IconResolution {
             IconResolution::Retina256 => -24,
             IconResolution::NonRetina512 => -24,
             IconResolution::Retina512 => -48,
+            IconResolution::Custom(size) => {
+                Self::interpolate(*size, |resolution| resolution.offset_y() as f64).round() as i32
+            }
         }
     }
 
@@ -210,6 +362,16 @@ impl IconResolution {
                 spread_px: 1,
                 page_y: 0,
             },
+            IconResolution::Custom(size) => BlurDown {
+                spread_px: Self::interpolate(*size, |resolution| {
+                    resolution.bottom_bezel_blur_down().spread_px as f64
+                })
+                .round() as u32,
+                page_y: Self::interpolate(*size, |resolution| {
+                    resolution.bottom_bezel_blur_down().page_y as f64
+                })
+                .round() as i32,
+            },
             _ => BlurDown {
                 spread_px: 2,
                 page_y: 1,
@@ -229,12 +391,135 @@ impl IconResolution {
             IconResolution::Retina256 => 0.75,
             IconResolution::NonRetina512 => 0.75,
             IconResolution::Retina512 => 0.75,
+            IconResolution::Custom(size) => {
+                Self::interpolate(*size, |resolution| resolution.bottom_bezel_alpha() as f64) as f32
+            }
+        }
+    }
+
+    /// Size of the square occupied by Apple's badges (e.g. `LockedBadgeIcon`), in pixels.
+    pub fn badge_size(&self) -> u32 {
+        match self {
+            IconResolution::NonRetina16 => 8,
+            IconResolution::Retina16 => 16,
+            IconResolution::NonRetina32 => 8,
+            IconResolution::Retina32 => 16,
+            IconResolution::NonRetina128 => 28,
+            IconResolution::Retina128 => 56,
+            IconResolution::NonRetina256 => 56,
+            IconResolution::Retina256 => 112,
+            IconResolution::NonRetina512 => 112,
+            IconResolution::Retina512 => 224,
+            IconResolution::Custom(size) => {
+                Self::interpolate(*size, |resolution| resolution.badge_size() as f64).round() as u32
+            }
+        }
+    }
+
+    /// Distance of Apple's badges from the edges of the icon, in pixels.
+    pub fn badge_margin(&self) -> i32 {
+        match self {
+            IconResolution::NonRetina16 => 0,
+            IconResolution::Retina16 => 0,
+            IconResolution::NonRetina32 => 0,
+            IconResolution::Retina32 => 0,
+            IconResolution::NonRetina128 => 2,
+            IconResolution::Retina128 => 3,
+            IconResolution::NonRetina256 => 3,
+            IconResolution::Retina256 => 6,
+            IconResolution::NonRetina512 => 6,
+            IconResolution::Retina512 => 12,
+            IconResolution::Custom(size) => {
+                Self::interpolate(*size, |resolution| resolution.badge_margin() as f64).round()
+                    as i32
+            }
+        }
+    }
+
+    /// Leaves room for multiple badges at small sizes, where a standard badge covers half of the icon.
+    pub fn badge_size_for_count(&self, num_badges: usize) -> u32 {
+        if num_badges > 1 && self.size() <= 32 {
+            return min(self.badge_size(), self.size() / 3);
         }
+        self.badge_size()
     }
 
     pub fn icon_file(&self) -> String {
         format!("icon_{}.png", self)
     }
+
+    /// Folder templates only exist for the `.iconset` resolutions. Other sizes are scaled down
+    /// from the next larger one.
+    pub fn template_resolution(&self) -> IconResolution {
+        match self {
+            IconResolution::Custom(size) => Self::by_pixel_size()
+                .into_iter()
+                .find(|resolution| resolution.size() >= *size)
+                .unwrap_or(IconResolution::Retina512),
+            resolution => *resolution,
+        }
+    }
+
+    pub fn is_iconset_resolution(&self) -> bool {
+        !matches!(self, IconResolution::Custom(_))
+    }
+
+    /// The resolution that is rendered for this one. A custom size that matches an `.iconset`
+    /// resolution (e.g. 64 for `32x32@2x`) reuses that icon instead of being rendered again.
+    pub fn rendered_resolution(&self) -> IconResolution {
+        match self {
+            IconResolution::Custom(size) => Self::by_pixel_size()
+                .into_iter()
+                .find(|resolution| resolution.size() == *size)
+                .unwrap_or(*self),
+            resolution => *resolution,
+        }
+    }
+
+    /// One `.iconset` resolution per pixel size, from small to large. For pixel sizes that occur
+    /// twice (e.g. `16x16@2x` and `32x32`), the non-Retina resolution is used.
+    fn by_pixel_size() -> Vec<IconResolution> {
+        let mut resolutions = Self::values();
+        resolutions.sort_by_key(|resolution| (resolution.size(), resolution.scale()));
+        resolutions.dedup_by_key(|resolution| resolution.size());
+        resolutions
+    }
+
+    /// Linearly interpolates a parameter between the `.iconset` resolutions with the closest pixel
+    /// sizes. Sizes outside the `.iconset` range use the parameter of the closest resolution.
+    fn interpolate(size: u32, parameter: impl Fn(&IconResolution) -> f64) -> f64 {
+        let resolutions = Self::by_pixel_size();
+        let mut previous = resolutions[0];
+        for resolution in resolutions {
+            if resolution.size() >= size {
+                if resolution.size() == previous.size() {
+                    return parameter(&resolution);
+                }
+                let fraction =
+                    (size - previous.size()) as f64 / (resolution.size() - previous.size()) as f64;
+                return parameter(&previous)
+                    + fraction * (parameter(&resolution) - parameter(&previous));
+            }
+            previous = resolution;
+        }
+        parameter(&previous)
+    }
+
+    /// Parses a name like `32x32` or `512x512@2x`.
+    pub fn from_name(name: &str) -> Option<IconResolution> {
+        Self::values()
+            .into_iter()
+            .find(|resolution| resolution.to_string() == name)
+    }
+
+    /// The `.iconset` resolution with the given pixel size (preferring non-Retina), or a custom
+    /// resolution for other sizes in the supported range.
+    pub fn from_pixel_size(size: u32) -> Option<IconResolution> {
+        if !CUSTOM_SIZE_RANGE.contains(&size) {
+            return None;
+        }
+        Some(IconResolution::Custom(size).rendered_resolution())
+    }
 }
 
 impl Display for IconResolution {
@@ -253,6 +538,7 @@ impl Display for IconResolution {
                 Self::Retina256 => "256x256@2x",
                 Self::NonRetina512 => "512x512",
                 Self::Retina512 => "512x512@2x",
+                Self::Custom(size) => return write!(f, "{}x{}", size, size),
             }
         )
     }
@@ -262,6 +548,10 @@ pub struct IconConversion {
     working_dir: PathBuf,
     resolution_prefix: String,
     pub progress_bar: Option<ProgressBar>,
+    progress_bar_type: ProgressBarType,
+    num_steps: u64,
+    position: Cell<u64>,
+    monitor: RenderMonitor,
 }
 
 impl IconConversion {
@@ -270,13 +560,26 @@ impl IconConversion {
             let wide_msg = format!("{:10} | {}", self.resolution_prefix, step_description);
             progress_bar.set_message(wide_msg);
         }
+        if let Some(progress_listener) = &self.monitor.progress_listener {
+            progress_listener(ProgressEvent {
+                progress_bar_type: self.progress_bar_type,
+                stage: self.resolution_prefix.clone(),
+                step: step_description.into(),
+                position: self.position.get(),
+                length: self.num_steps,
+            });
+        }
     }
 
-    pub fn step(&self, step_desciption: &str) {
+    /// Also stops the conversion if the render was cancelled.
+    pub fn step(&self, step_desciption: &str) -> Result<(), FolderifyError> {
+        self.monitor.check_cancelled()?;
+        self.position.set(self.position.get() + 1);
+        self.step_unincremented(step_desciption);
         if let Some(progress_bar) = &self.progress_bar {
-            self.step_unincremented(step_desciption);
             progress_bar.inc(1);
         }
+        Ok(())
     }
 
     fn output_path(&self, file_name: &str) -> PathBuf {
@@ -293,8 +596,44 @@ impl IconConversion {
         self.step_unincremented("Preparing icon mask");
         let mut args = CommandArgs::new();
         args.background_transparent();
-        args.density(density(&options.mask_path, centering_dimensions)?);
-        args.push_path(&options.mask_path);
+        match &options.text_mask {
+            Some(text_mask) => {
+                args.fill(&RGBColor::new(0, 0, 0));
+                if let Some(font) = &text_mask.font {
+                    args.font(font);
+                }
+                match text_mask.uses_pango() {
+                    true => {
+                        if !magick_supports_format("PANGO")? {
+                            return Err(FolderifyError::General(GeneralError {
+                                message: "`--emoji` requires ImageMagick with Pango support (or pass an emoji font file using `--font`).".into(),
+                            }));
+                        }
+                        // `pango:` doesn't fit the text to a `-size` (it wraps and clips it), so
+                        // it is rendered at its natural size (with each line about as tall as the
+                        // mask area), and then fitted by trimming and resizing.
+                        args.pointsize(centering_dimensions.height);
+                    }
+                    false => {
+                        // Rendered at 2x so that trimming doesn't leave too few pixels to resize.
+                        args.size(&Dimensions {
+                            width: centering_dimensions.width * 2,
+                            height: centering_dimensions.height * 2,
+                        });
+                    }
+                }
+                args.center();
+                args.push_string(text_mask.label());
+            }
+            None => {
+                let mask_path = match options.mask_is_stdin() {
+                    true => self.stdin_mask()?,
+                    false => options.mask_path.clone(),
+                };
+                args.density(density(&mask_path, centering_dimensions)?);
+                args.push_path(&mask_path);
+            }
+        }
         if !options.no_trim {
             args.trim()
         }
@@ -304,7 +643,24 @@ impl IconConversion {
         let output_path = self.output_path("0.0_FULL_MASK.png");
         args.push_path(&output_path);
         run_magick(&args, None)?;
-        self.step("");
+        self.step("")?;
+        Ok(output_path)
+    }
+
+    /// Saves the mask from stdin, so that it can be read more than once.
+    fn stdin_mask(&self) -> Result<PathBuf, FolderifyError> {
+        let mut mask = Vec::<u8>::new();
+        stdin().read_to_end(&mut mask).map_err(|_| {
+            FolderifyError::General(GeneralError {
+                message: "Could not read the mask from stdin.".into(),
+            })
+        })?;
+        let output_path = self.output_path("0.0_STDIN_MASK");
+        fs::write(&output_path, mask).map_err(|_| {
+            FolderifyError::General(GeneralError {
+                message: "Could not save the mask from stdin.".into(),
+            })
+        })?;
         Ok(output_path)
     }
 
@@ -316,11 +672,40 @@ impl IconConversion {
         let mut args = CommandArgs::new();
         args.background_transparent();
         args.push_path(input_path);
+        self.sized_mask_with_args(args, inputs)
+    }
+
+    /// Like `sized_mask`, but for a hand-tuned mask file (instead of the prepared full mask).
+    pub fn sized_mask_override(
+        &self,
+        options: &Options,
+        mask_override_path: &Path,
+        inputs: &ScaledMaskInputs,
+    ) -> Result<PathBuf, FolderifyError> {
+        let mut args = CommandArgs::new();
+        args.background_transparent();
+        args.density(density(mask_override_path, &inputs.mask_dimensions)?);
+        args.push_path(mask_override_path);
+        if !options.no_trim {
+            args.trim()
+        }
+        self.sized_mask_with_args(args, inputs)
+    }
+
+    fn sized_mask_with_args(
+        &self,
+        mut args: CommandArgs,
+        inputs: &ScaledMaskInputs,
+    ) -> Result<PathBuf, FolderifyError> {
         args.resize(&inputs.mask_dimensions);
+        if !matches!(inputs.mask_gravity, Gravity::Center) {
+            args.gravity(&inputs.mask_gravity);
+            args.extent(&Extent::no_offset(&inputs.mask_dimensions));
+        }
         args.center();
         args.extent(&Extent {
             size: Dimensions::square(inputs.icon_size),
-            offset: Offset::from_y(inputs.offset_y),
+            offset: inputs.offset.clone(),
         });
         let output_path = self.output_path("1.0_SIZED_MASK.png");
         args.push_path(&output_path);
@@ -351,7 +736,7 @@ impl IconConversion {
         output_path: &Path,
         inputs: &EngravingInputs,
     ) -> Result<(), FolderifyError> {
-        self.step("Creating colorized fill");
+        self.step("Creating colorized fill")?;
         let fill_colorized = self.simple_operation(
             sized_mask,
             "2.1_FILL_COLORIZED",
@@ -360,13 +745,13 @@ impl IconConversion {
             },
         )?;
 
-        self.step("Setting fill opacity");
+        self.step("Setting fill opacity")?;
         let fill =
             self.simple_operation(&fill_colorized, "2.2_FILL", |args: &mut CommandArgs| {
                 args.opacity(0.5);
             })?;
 
-        self.step("Complementing mask for top bezel");
+        self.step("Complementing mask for top bezel")?;
         let top_bezel_complement = self.simple_operation(
             sized_mask,
             "3.1_TOP_BEZEL_COMPLEMENT",
@@ -375,7 +760,7 @@ impl IconConversion {
             },
         )?;
 
-        self.step("Colorizing top bezel");
+        self.step("Colorizing top bezel")?;
         let top_bezel_colorized = self.simple_operation(
             &top_bezel_complement,
             "3.2_TOP_BEZEL_COLORIZED",
@@ -384,7 +769,7 @@ impl IconConversion {
             },
         )?;
 
-        self.step("Blurring top bezel");
+        self.step("Blurring top bezel")?;
         let top_bezel_blurred = self.simple_operation(
             &top_bezel_colorized,
             "3.3_TOP_BEZEL_BLURRED",
@@ -393,7 +778,7 @@ impl IconConversion {
             },
         )?;
 
-        self.step("Compositing top bezel");
+        self.step("Compositing top bezel")?;
         let top_bezel_masked = self.simple_operation(
             &top_bezel_blurred,
             "3.4_TOP_BEZEL_MASKED",
@@ -402,7 +787,7 @@ impl IconConversion {
             },
         )?;
 
-        self.step("Setting top bezel opacity");
+        self.step("Setting top bezel opacity")?;
         let top_bezel = self.simple_operation(
             &top_bezel_masked,
             "3.5_TOP_BEZEL",
@@ -411,7 +796,7 @@ impl IconConversion {
             },
         )?;
 
-        self.step("Colorizing bottom bezel");
+        self.step("Colorizing bottom bezel")?;
         let bottom_bezel_colorized = self.simple_operation(
             sized_mask,
             "4.1_BOTTOM_BEZEL_COLORIZED",
@@ -420,7 +805,7 @@ impl IconConversion {
             },
         )?;
 
-        self.step("Blurring bottom bezel");
+        self.step("Blurring bottom bezel")?;
         let bottom_bezel_blurred = self.simple_operation(
             &bottom_bezel_colorized,
             "4.2_BOTTOM_BEZEL_BLURRED",
@@ -429,7 +814,7 @@ impl IconConversion {
             },
         )?;
 
-        self.step("Compositing bottom bezel");
+        self.step("Compositing bottom bezel")?;
         let bottom_bezel_masked = self.simple_operation(
             &bottom_bezel_blurred,
             "4.3_BOTTOM_BEZEL_MASKED",
@@ -438,7 +823,7 @@ impl IconConversion {
             },
         )?;
 
-        self.step("Setting bottom bezel opacity");
+        self.step("Setting bottom bezel opacity")?;
         let bottom_bezel = self.simple_operation(
             &bottom_bezel_masked,
             "4.4_BOTTOM_BEZEL",
@@ -447,7 +832,7 @@ impl IconConversion {
             },
         )?;
 
-        self.step("Engraving bezels");
+        self.step("Engraving bezels")?;
         let mut args = CommandArgs::new();
         args.push("-");
         args.push_path(&bottom_bezel);
@@ -461,22 +846,184 @@ impl IconConversion {
         Ok(())
     }
 
+    /// Places the (full-color) sized mask onto the folder, instead of engraving it.
+    pub fn place_artwork(
+        &self,
+        sized_mask: &Path,
+        template_icon: &[u8],
+        output_path: &Path,
+        shadow_inputs: Option<&ShadowInputs>,
+    ) -> Result<(), FolderifyError> {
+        let shadow = match shadow_inputs {
+            Some(shadow_inputs) => {
+                self.step("Colorizing shadow")?;
+                let shadow_colorized = self.simple_operation(
+                    sized_mask,
+                    "2.1_SHADOW_COLORIZED",
+                    |args: &mut CommandArgs| {
+                        args.fill_colorize(&RGBColor::new(0, 0, 0));
+                    },
+                )?;
+
+                self.step("Blurring shadow")?;
+                let shadow_blurred = self.simple_operation(
+                    &shadow_colorized,
+                    "2.2_SHADOW_BLURRED",
+                    |args: &mut CommandArgs| {
+                        args.blur_down(&shadow_inputs.blur);
+                    },
+                )?;
+
+                self.step("Setting shadow opacity")?;
+                Some(self.simple_operation(
+                    &shadow_blurred,
+                    "2.3_SHADOW",
+                    |args: &mut CommandArgs| {
+                        args.opacity(shadow_inputs.opacity);
+                    },
+                )?)
+            }
+            None => None,
+        };
+
+        self.step("Placing artwork")?;
+        let mut args = CommandArgs::new();
+        args.push("-");
+        if let Some(shadow) = shadow {
+            args.push_path(&shadow);
+            args.composite(&CompositingOperation::dissolve);
+        }
+        args.push_path(sized_mask);
+        args.composite(&CompositingOperation::dissolve);
+        args.push_path(output_path);
+        run_magick(&args, Some(template_icon))?;
+        Ok(())
+    }
+
     pub fn badge_in_place(
         &self,
         icon_path: &Path,
-        badge: Badge,
+        badge: &BadgeSpec,
+        badge_index: usize,
+        num_badges: usize,
         resolution: &IconResolution,
     ) -> Result<(), FolderifyError> {
-        self.step("Adding badge");
+        self.step("Adding badge")?;
+
+        let badge_size = resolution.badge_size_for_count(num_badges);
+
+        // The built-in badges are already placed at the bottom left, at their standard size.
+        if let (BadgeSource::BuiltIn(built_in_badge), BadgePosition::BottomLeft) =
+            (&badge.source, badge.position)
+        {
+            if badge_size == resolution.badge_size() && resolution.is_iconset_resolution() {
+                let badge_icon = get_badge_icon(*built_in_badge, resolution);
+
+                let mut args = CommandArgs::new();
+                args.push_path(icon_path);
+                args.push("-");
+                args.composite(&CompositingOperation::dissolve);
+                args.push_path(icon_path);
+                return run_magick(&args, Some(badge_icon));
+            }
+        }
 
-        let badge_icon = get_badge_icon(badge, resolution);
+        let sized_badge = self.sized_badge(&badge.source, badge_index, badge_size, resolution)?;
 
+        let margin = resolution.badge_margin();
+        let (gravity, offset) = match badge.position {
+            BadgePosition::BottomLeft => (
+                Gravity::SouthWest,
+                Offset {
+                    x: margin,
+                    y: margin,
+                },
+            ),
+            BadgePosition::BottomRight => (
+                Gravity::SouthEast,
+                Offset {
+                    x: margin,
+                    y: margin,
+                },
+            ),
+            BadgePosition::TopRight => (
+                Gravity::NorthEast,
+                Offset {
+                    x: margin,
+                    y: margin,
+                },
+            ),
+            BadgePosition::Center => (Gravity::Center, Offset::from_y(0)),
+        };
         let mut args = CommandArgs::new();
         args.push_path(icon_path);
-        args.push("-");
+        args.push_path(&sized_badge);
+        args.gravity(&gravity);
+        args.geometry(&offset);
         args.composite(&CompositingOperation::dissolve);
         args.push_path(icon_path);
-        run_magick(&args, Some(badge_icon))
+        run_magick(&args, None)
+    }
+
+    pub fn sized_badge(
+        &self,
+        badge_source: &BadgeSource,
+        badge_index: usize,
+        badge_size: u32,
+        resolution: &IconResolution,
+    ) -> Result<PathBuf, FolderifyError> {
+        let badge_dimensions = Dimensions::square(badge_size);
+
+        let mut args = CommandArgs::new();
+        args.background_transparent();
+        let stdin = match badge_source {
+            BadgeSource::BuiltIn(built_in_badge) => {
+                args.push("-");
+                Some(get_badge_icon(*built_in_badge, resolution))
+            }
+            BadgeSource::Image(badge_image_path) => {
+                // Use the matching size if an `.iconset` is given.
+                let input_path = match metadata(badge_image_path) {
+                    Ok(badge_image_metadata) if badge_image_metadata.is_dir() => {
+                        badge_image_path.join(resolution.template_resolution().icon_file())
+                    }
+                    _ => badge_image_path.to_owned(),
+                };
+                args.density(density(&input_path, &badge_dimensions)?);
+                args.push_path(&input_path);
+                None
+            }
+        };
+        args.trim();
+        args.resize(&badge_dimensions);
+        args.center();
+        args.extent(&Extent::no_offset(&badge_dimensions));
+        let output_path = self.output_path(&format!("5.{}_SIZED_BADGE.png", badge_index + 1));
+        args.push_path(&output_path);
+        run_magick(&args, stdin)?;
+        Ok(output_path)
+    }
+
+    /// The folder template at the exact size of the icon.
+    fn sized_template(
+        &self,
+        icon_inputs: &IconInputs,
+    ) -> Result<Cow<'static, [u8]>, FolderifyError> {
+        let template_icon = get_folder_icon(icon_inputs);
+        if icon_inputs.resolution.is_iconset_resolution() {
+            return Ok(Cow::Borrowed(template_icon));
+        }
+        self.step_unincremented("Sizing folder template");
+        let mut args = CommandArgs::new();
+        args.background_transparent();
+        args.push("-");
+        args.resize(&Dimensions::square(icon_inputs.resolution.size()));
+        args.push("png:-");
+        Ok(Cow::Owned(run_command(
+            MAGICK_COMMAND,
+            &args,
+            Some(template_icon),
+        )?))
     }
 
     // TODO
@@ -488,73 +1035,89 @@ impl IconConversion {
         icon_inputs: &IconInputs,
     ) -> Result<(), FolderifyError> {
         // if options.verbose {
-        //     println!("[Starting] {}", inputs.resolution);
+        //     status!("[Starting] {}", inputs.resolution);
         // }
 
         let size = icon_inputs.resolution.size();
-        let offset_y = icon_inputs.resolution.offset_y();
+        let mask_placement = &options.mask_placement;
 
         self.step_unincremented("Sizing mask");
-        let sized_mask_path = self
-            .sized_mask(
-                full_mask_path,
-                &ScaledMaskInputs {
-                    icon_size: size,
-                    mask_dimensions: Dimensions {
-                        width: size * 3 / 4,
-                        height: size / 2,
-                    },
-                    offset_y,
-                },
-            )
-            .unwrap();
+        let scaled_mask_inputs = ScaledMaskInputs {
+            icon_size: size,
+            mask_dimensions: mask_placement.mask_dimensions(&icon_inputs.resolution),
+            mask_gravity: mask_placement.gravity(),
+            offset: mask_placement.extent_offset(&icon_inputs.resolution),
+        };
+        let sized_mask_path = match options.mask_override(&icon_inputs.resolution) {
+            Some(mask_override_path) => {
+                self.sized_mask_override(options, mask_override_path, &scaled_mask_inputs)
+            }
+            None => self.sized_mask(full_mask_path, &scaled_mask_inputs),
+        }?;
 
         // TODO
-        let template_icon = get_folder_icon(icon_inputs);
-
-        let fill_color = match (icon_inputs.folder_style, icon_inputs.color_scheme) {
-            (FolderStyle::Tahoe, _) => RGBColor::new(74, 141, 172),
-            (_, ColorScheme::Light) => RGBColor::new(8, 134, 206),
-            (_, ColorScheme::Dark) => RGBColor::new(6, 111, 194),
-        };
+        let template_icon = self.sized_template(icon_inputs)?;
+        let template_icon = template_icon.as_ref();
+
+        let drawn = match options.mode {
+            RenderMode::Engrave => {
+                let fill_color = match (icon_inputs.folder_style, icon_inputs.color_scheme) {
+                    (FolderStyle::Tahoe, _) => RGBColor::new(74, 141, 172),
+                    (FolderStyle::Adwaita, _) => RGBColor::new(28, 113, 216),
+                    (_, ColorScheme::Light) => RGBColor::new(8, 134, 206),
+                    (_, ColorScheme::Dark) => RGBColor::new(6, 111, 194),
+                };
 
-        let engraved = self.engrave(
-            &sized_mask_path,
-            template_icon,
-            output_path,
-            &EngravingInputs {
-                fill_color,
-                top_bezel: BezelInputs {
-                    color: RGBColor::new(58, 152, 208),
-                    blur: BlurDown {
-                        spread_px: 0,
-                        page_y: 2,
+                self.engrave(
+                    &sized_mask_path,
+                    template_icon,
+                    output_path,
+                    &EngravingInputs {
+                        fill_color,
+                        top_bezel: BezelInputs {
+                            color: RGBColor::new(58, 152, 208),
+                            blur: BlurDown {
+                                spread_px: 0,
+                                page_y: 2,
+                            },
+                            mask_operation: CompositingOperation::Dst_In,
+                            opacity: 0.5,
+                        },
+                        bottom_bezel: BezelInputs {
+                            color: RGBColor::new(174, 225, 253),
+                            blur: icon_inputs.resolution.bottom_bezel_blur_down(),
+                            mask_operation: CompositingOperation::Dst_Out,
+                            opacity: icon_inputs.resolution.bottom_bezel_alpha(),
+                        },
                     },
-                    mask_operation: CompositingOperation::Dst_In,
-                    opacity: 0.5,
-                },
-                bottom_bezel: BezelInputs {
-                    color: RGBColor::new(174, 225, 253),
-                    blur: icon_inputs.resolution.bottom_bezel_blur_down(),
-                    mask_operation: CompositingOperation::Dst_Out,
-                    opacity: icon_inputs.resolution.bottom_bezel_alpha(),
-                },
-            },
-        );
-        if let Some(badge) = options.badge {
-            self.badge_in_place(output_path, badge, &icon_inputs.resolution)?;
+                )
+            }
+            RenderMode::Color => self.place_artwork(
+                &sized_mask_path,
+                template_icon,
+                output_path,
+                match options.shadow {
+                    true => Some(ShadowInputs {
+                        blur: icon_inputs.resolution.bottom_bezel_blur_down(),
+                        opacity: 0.35,
+                    }),
+                    false => None,
+                }
+                .as_ref(),
+            ),
         };
-
-        self.step("");
-
-        if options.verbose {
-            println!(
-                "[{}] {}",
-                options.mask_path.display(),
-                icon_inputs.resolution
-            );
+        for (badge_index, badge) in options.badges.iter().enumerate() {
+            self.badge_in_place(
+                output_path,
+                badge,
+                badge_index,
+                options.badges.len(),
+                &icon_inputs.resolution,
+            )?;
         }
-        engraved
+
+        self.step("")?;
+        drawn
     }
 
     pub fn to_icns(
@@ -563,9 +1126,9 @@ impl IconConversion {
         iconset_dir: &Path,
         icns_path: &Path,
     ) -> Result<(), FolderifyError> {
-        self.step("Creating .icns file");
+        self.step("Creating .icns file")?;
         if options.verbose {
-            println!(
+            status!(
                 "[{}] Creating the .icns file...",
                 options.mask_path.display()
             );
@@ -576,7 +1139,252 @@ impl IconConversion {
         args.push("icns");
         args.push("--output");
         args.push_path(icns_path);
-        run_command(ICONUTIL_COMMAND, &args, None)?;
+        run_command(ICONUTIL_COMMAND, &args, None).map_err(|e| match e {
+            FolderifyError::CommandInvalid(_) => FolderifyError::General(GeneralError {
+                message: "Creating `.icns` files requires `iconutil` (macOS). Use `--output-iconset` or `--output-png` instead.".into(),
+            }),
+            e => e,
+        })?;
+        if options.icns_legacy_chunks {
+            self.add_legacy_icns_chunks(iconset_dir, icns_path)?;
+        }
+        Ok(())
+    }
+
+    fn add_legacy_icns_chunks(
+        &self,
+        iconset_dir: &Path,
+        icns_path: &Path,
+    ) -> Result<(), FolderifyError> {
+        self.step_unincremented("Adding legacy .icns chunks");
+        let icns_error = |message: &str| {
+            FolderifyError::General(GeneralError {
+                message: message.into(),
+            })
+        };
+        let mut chunks = Vec::<(&[u8; 4], Vec<u8>)>::new();
+        for legacy_icon in LegacyIcon::values() {
+            let mut args = CommandArgs::new();
+            args.push_path(&iconset_dir.join(legacy_icon.resolution().icon_file()));
+            args.push("-depth");
+            args.push("8");
+            args.push("rgba:-");
+            let rgba = run_command(MAGICK_COMMAND, &args, None)?;
+            chunks.extend(icns::legacy_chunks(legacy_icon, &rgba));
+        }
+        let icns =
+            fs::read(icns_path).map_err(|_| icns_error("Could not read the `.icns` file."))?;
+        let icns =
+            icns::with_legacy_chunks(&icns, &chunks).map_err(|message| icns_error(&message))?;
+        fs::write(icns_path, icns).map_err(|_| icns_error("Could not write the `.icns` file."))
+    }
+
+    /// Embeds the `.icns` file for dark mode into the (light) `.icns` file.
+    pub fn embed_dark_icns(
+        &self,
+        options: &Options,
+        icns_path: &Path,
+        dark_icns_path: &Path,
+    ) -> Result<(), FolderifyError> {
+        self.step_unincremented("Embedding dark .icns file");
+        if options.verbose {
+            status!(
+                "[{}] Embedding the dark .icns file...",
+                options.mask_path.display()
+            );
+        }
+        let icns_error = |message: &str| {
+            FolderifyError::General(GeneralError {
+                message: message.into(),
+            })
+        };
+        let read_icns = |path: &Path| {
+            fs::read(path).map_err(|_| icns_error("Could not read the `.icns` file."))
+        };
+        let icns = icns::with_dark_variant(&read_icns(icns_path)?, &read_icns(dark_icns_path)?)
+            .map_err(|message| icns_error(&message))?;
+        fs::write(icns_path, icns).map_err(|_| icns_error("Could not write the `.icns` file."))
+    }
+
+    /// `rendered_icon` gives the path of the rendered icon for each resolution in the theme.
+    pub fn to_icon_theme(
+        &self,
+        options: &Options,
+        rendered_icon: impl Fn(&IconResolution) -> PathBuf,
+        theme_dir: &Path,
+    ) -> Result<(), FolderifyError> {
+        self.step("Writing icon theme")?;
+        if options.verbose {
+            status!(
+                "[{}] Writing the icon theme...",
+                options.mask_path.display()
+            );
+        }
+        let write_error = || {
+            FolderifyError::General(GeneralError {
+                message: "Could not write icon theme".into(),
+            })
+        };
+        for resolution in icon_theme::resolutions() {
+            let icon_path = icon_theme::icon_path(theme_dir, &resolution, &options.icon_name);
+            create_dir_all(icon_path.parent().unwrap()).map_err(|_| write_error())?;
+            fs::copy(rendered_icon(&resolution), &icon_path).map_err(|_| write_error())?;
+        }
+
+        let index_theme_path = icon_theme::index_theme_path(theme_dir);
+        if metadata(&index_theme_path).is_ok() {
+            // Don't clobber an existing theme (e.g. when writing into `~/.local/share/icons/hicolor`).
+            eprintln!(
+                "Note: `{}` already exists and was left unchanged.",
+                index_theme_path.display()
+            );
+        } else {
+            let theme_name = theme_dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| options.icon_name.clone());
+            fs::write(index_theme_path, icon_theme::index_theme(&theme_name))
+                .map_err(|_| write_error())?;
+        }
+        Ok(())
+    }
+
+    pub fn to_contact_sheet(
+        &self,
+        options: &Options,
+        variant_iconsets: &[(&IconVariant, &Path)],
+        output_path: &Path,
+    ) -> Result<(), FolderifyError> {
+        self.step("Writing contact sheet")?;
+        if options.verbose {
+            status!(
+                "[{}] Writing the contact sheet...",
+                options.mask_path.display()
+            );
+        }
+        run_magick(
+            &contact_sheet::contact_sheet_args(variant_iconsets, output_path),
+            None,
+        )
+    }
+
+    pub fn to_animation(
+        &self,
+        options: &Options,
+        iconset_dir: &Path,
+        output_path: &Path,
+        format: AnimationFormat,
+    ) -> Result<(), FolderifyError> {
+        self.step("Writing animation")?;
+        if options.verbose {
+            status!("[{}] Writing the animation...", options.mask_path.display());
+        }
+        let mut frame_paths = Vec::<PathBuf>::new();
+        for resolution in IconResolution::values().iter().rev() {
+            let frame_path =
+                self.output_path(&format!("ANIMATION_FRAME_{}", resolution.icon_file()));
+            run_magick(
+                &animation::frame_args(
+                    &iconset_dir.join(resolution.icon_file()),
+                    resolution,
+                    format,
+                    &frame_path,
+                ),
+                None,
+            )?;
+            frame_paths.push(frame_path);
+        }
+        let write_error = |message: &str| {
+            FolderifyError::General(GeneralError {
+                message: message.into(),
+            })
+        };
+        match format {
+            AnimationFormat::Gif => {
+                let frame_paths: Vec<&Path> = frame_paths.iter().map(PathBuf::as_path).collect();
+                run_magick(&animation::gif_args(&frame_paths, output_path), None)
+            }
+            AnimationFormat::Apng => {
+                let frames = frame_paths
+                    .iter()
+                    .map(fs::read)
+                    .collect::<Result<Vec<Vec<u8>>, _>>()
+                    .map_err(|_| write_error("Could not read animation frames"))?;
+                let apng = animation::apng(&frames).map_err(|message| write_error(&message))?;
+                fs::write(output_path, apng).map_err(|_| write_error("Could not write animation"))
+            }
+        }
+    }
+
+    /// Copies rendered icons (`.iconset` sizes or custom sizes) to their `--output-png` paths.
+    pub fn to_pngs(
+        &self,
+        options: &Options,
+        rendered_pngs: &[(PathBuf, PathBuf)],
+    ) -> Result<(), FolderifyError> {
+        self.step("Writing .png files")?;
+        if options.verbose {
+            status!("[{}] Writing .png files...", options.mask_path.display());
+        }
+        for (rendered_png, output_png) in rendered_pngs {
+            fs::copy(rendered_png, output_png).map_err(|_| {
+                FolderifyError::General(GeneralError {
+                    message: format!("Could not write .png file: {}", output_png.display()),
+                })
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Each rendered iconset is paired with its appearance, which is `None` if only a single color scheme is written.
+    pub fn to_xcassets(
+        &self,
+        options: &Options,
+        iconset_dirs: &[(Option<ColorScheme>, &Path)],
+        xcassets_dir: &Path,
+    ) -> Result<(), FolderifyError> {
+        self.step("Writing asset catalog")?;
+        if options.verbose {
+            status!(
+                "[{}] Writing the asset catalog...",
+                options.mask_path.display()
+            );
+        }
+        let write_error = || {
+            FolderifyError::General(GeneralError {
+                message: "Could not write asset catalog".into(),
+            })
+        };
+        let asset_dir = xcassets::iconset_dir(xcassets_dir, &options.asset_name);
+        create_dir_all(&asset_dir).map_err(|_| write_error())?;
+
+        let catalog_contents_json_path = xcassets::contents_json_path(xcassets_dir);
+        if metadata(&catalog_contents_json_path).is_err() {
+            fs::write(
+                catalog_contents_json_path,
+                xcassets::catalog_contents_json(),
+            )
+            .map_err(|_| write_error())?;
+        }
+
+        for (appearance, iconset_dir) in iconset_dirs {
+            for resolution in IconResolution::values() {
+                fs::copy(
+                    iconset_dir.join(resolution.icon_file()),
+                    asset_dir.join(xcassets::image_file_name(&resolution, *appearance)),
+                )
+                .map_err(|_| write_error())?;
+            }
+        }
+        let appearances: Vec<Option<ColorScheme>> = iconset_dirs
+            .iter()
+            .map(|(appearance, _)| *appearance)
+            .collect();
+        fs::write(
+            xcassets::contents_json_path(&asset_dir),
+            xcassets::iconset_contents_json(&appearances),
+        )
+        .map_err(|_| write_error())?;
         Ok(())
     }
 
@@ -587,7 +1395,7 @@ impl IconConversion {
         target_path: &Path,
     ) -> Result<(), FolderifyError> {
         if options.verbose {
-            println!(
+            status!(
                 "[{}] Assigning icon to target: {}",
                 options.mask_path.display(),
                 target_path.display(),
@@ -598,10 +1406,15 @@ impl IconConversion {
             SetIconUsing::Fileicon => Self::assign_icns_using_fileicon,
             SetIconUsing::Osascript => Self::assign_icns_using_osascript,
             SetIconUsing::Rez => Self::assign_icns_using_rez,
+            SetIconUsing::Gio | SetIconUsing::Kde => {
+                return Err(FolderifyError::General(GeneralError {
+                    message: "This program assigns icons using a `.png` file".into(),
+                }))
+            }
         };
         assignment_fn(self, options, icns_path, target_path)?;
 
-        self.step("");
+        self.step("")?;
 
         Ok(())
     }
@@ -612,13 +1425,14 @@ impl IconConversion {
         icns_path: &Path,
         target_path: &Path,
     ) -> Result<(), FolderifyError> {
-        self.step("Using `osascript` to assign the `.icns` file.");
+        self.step("Using `osascript` to assign the `.icns` file.")?;
 
         let target_metadata = match metadata(target_path) {
             Ok(target_metadata) => target_metadata,
             Err(_) => {
-                eprintln!("Error: target path does not exist!");
-                exit(1);
+                return Err(FolderifyError::General(GeneralError {
+                    message: "Target path does not exist.".into(),
+                }))
             }
         };
 
@@ -648,8 +1462,9 @@ impl IconConversion {
             if metadata(target_path.join("Icon\r")).is_err()
                 && metadata(target_path.join(".VolumeIcon.icns")).is_err()
             {
-                eprintln!("Icon was not successfully assigned to the target folder.");
-                exit(1);
+                return Err(FolderifyError::General(GeneralError {
+                    message: "Icon was not successfully assigned to the target folder.".into(),
+                }));
             }
         } else if options.target.is_some() {
             // TODO: this is usually overwritten by the progress bars.
@@ -667,7 +1482,7 @@ impl IconConversion {
         icns_path: &Path,
         target_path: &Path,
     ) -> Result<(), FolderifyError> {
-        self.step("Using `fileicon` to assign the `.icns` file.");
+        self.step("Using `fileicon` to assign the `.icns` file.")?;
         let mut args = CommandArgs::new();
         args.push("set");
         args.push_path(target_path);
@@ -684,8 +1499,12 @@ impl IconConversion {
         target_path: &Path,
     ) -> Result<(), FolderifyError> {
         let target_is_dir = metadata(target_path)
-            .expect("Target does not exist!")
-            .is_dir(); // TODO: Return `FolderifyError`
+            .map_err(|_| {
+                FolderifyError::General(GeneralError {
+                    message: "Target path does not exist.".into(),
+                })
+            })?
+            .is_dir();
 
         let target_resource_path = if target_is_dir {
             target_path.join("Icon\r")
@@ -694,14 +1513,14 @@ impl IconConversion {
         };
 
         // sips: add an icns resource fork to the icns file
-        self.step("Adding resource fork to .icns file using sips");
+        self.step("Adding resource fork to .icns file using sips")?;
         let mut args = CommandArgs::new();
         args.push("-i");
         args.push_path(icns_path);
         run_command(SIPS_COMMAND, &args, None)?;
 
         // DeRez: export the icns resource from the icns file
-        self.step("Exporting .icns resource using DeRez");
+        self.step("Exporting .icns resource using DeRez")?;
         let mut args = CommandArgs::new();
         args.push("-only");
         args.push("icns");
@@ -715,7 +1534,7 @@ impl IconConversion {
         }
 
         // Rez: add exported icns resource to the resource fork of target/Icon^M
-        self.step("Add .icns resource target using Rez");
+        self.step("Add .icns resource target using Rez")?;
         let mut args = CommandArgs::new();
         args.push("-append");
         args.push_path(&derezzed_path);
@@ -724,7 +1543,7 @@ impl IconConversion {
         run_command(REZ_COMMAND, &args, None)?;
 
         // SetFile: set custom icon attribute
-        self.step("Setting custom icon attribute");
+        self.step("Setting custom icon attribute")?;
         let mut args = CommandArgs::new();
         args.push("-a");
         args.push("-C");
@@ -732,7 +1551,7 @@ impl IconConversion {
         run_command(SETFILE_COMMAND, &args, None)?;
 
         if target_is_dir {
-            self.step("Setting invisible file attribute");
+            self.step("Setting invisible file attribute")?;
             // SetFile: set invisible file attribute
             let mut args = CommandArgs::new();
             args.push("-a");
@@ -740,14 +1559,321 @@ impl IconConversion {
             args.push_path(&target_resource_path);
             run_command(SETFILE_COMMAND, &args, None)?;
         } else {
-            self.step("Skipping invisible file attribute for file target");
+            self.step("Skipping invisible file attribute for file target")?;
         };
 
         Ok(())
     }
+
+    pub fn assign_png(
+        &self,
+        options: &Options,
+        png_path: &Path,
+        target_path: &Path,
+    ) -> Result<(), FolderifyError> {
+        if options.verbose {
+            status!(
+                "[{}] Assigning icon to target: {}",
+                options.mask_path.display(),
+                target_path.display(),
+            );
+        }
+
+        let assignment_fn = match options.set_icon_using {
+            SetIconUsing::Gio => Self::assign_png_using_gio,
+            SetIconUsing::Kde => Self::assign_png_using_kde_directory,
+            _ => {
+                return Err(FolderifyError::General(GeneralError {
+                    message: "This program assigns icons using an `.icns` file".into(),
+                }))
+            }
+        };
+        assignment_fn(self, options, png_path, target_path)?;
+
+        self.step("")?;
+
+        Ok(())
+    }
+
+    // Freedesktop file managers reference the icon by path, so we place a copy next to (or inside) the target.
+    fn copy_png_for_target(
+        &self,
+        png_path: &Path,
+        target_path: &Path,
+    ) -> Result<PathBuf, FolderifyError> {
+        self.step("Copying the `.png` icon for the target")?;
+        let target_metadata = match metadata(target_path) {
+            Ok(target_metadata) => target_metadata,
+            Err(_) => {
+                return Err(FolderifyError::General(GeneralError {
+                    message: "Target path does not exist".into(),
+                }))
+            }
+        };
+        let icon_path = if target_metadata.is_dir() {
+            target_path.join(FREEDESKTOP_FOLDER_ICON_FILE_NAME)
+        } else {
+            let file_name = target_path.file_name().unwrap_or_default();
+            target_path.with_file_name(format!(".{}.png", file_name.to_string_lossy()))
+        };
+        if fs::copy(png_path, &icon_path).is_err() {
+            return Err(FolderifyError::General(GeneralError {
+                message: "Could not copy the `.png` icon for the target".into(),
+            }));
+        }
+        match icon_path.canonicalize() {
+            Ok(icon_path) => Ok(icon_path),
+            Err(_) => Err(FolderifyError::General(GeneralError {
+                message: "Could not resolve the `.png` icon path".into(),
+            })),
+        }
+    }
+
+    pub fn assign_png_using_gio(
+        &self,
+        _options: &Options,
+        png_path: &Path,
+        target_path: &Path,
+    ) -> Result<(), FolderifyError> {
+        let icon_path = self.copy_png_for_target(png_path, target_path)?;
+
+        self.step("Using `gio` to set the custom icon attribute")?;
+        let mut args = CommandArgs::new();
+        args.push("set");
+        args.push("-t");
+        args.push("string");
+        args.push_path(target_path);
+        args.push("metadata::custom-icon");
+        args.push_string(file_uri(&icon_path));
+        run_command(GIO_COMMAND, &args, None)?;
+
+        Ok(())
+    }
+
+    pub fn assign_png_using_kde_directory(
+        &self,
+        _options: &Options,
+        png_path: &Path,
+        target_path: &Path,
+    ) -> Result<(), FolderifyError> {
+        if !metadata(target_path).is_ok_and(|m| m.is_dir()) {
+            return Err(FolderifyError::General(GeneralError {
+                message: "KDE `.directory` entries can only be assigned to a folder".into(),
+            }));
+        }
+        let icon_path = self.copy_png_for_target(png_path, target_path)?;
+
+        self.step("Writing the `.directory` entry")?;
+        let directory_entry_path = target_path.join(".directory");
+        let existing = fs::read_to_string(&directory_entry_path).unwrap_or_default();
+        let contents = directory_entry_with_icon(&existing, &icon_path.to_string_lossy());
+        if fs::write(&directory_entry_path, contents).is_err() {
+            return Err(FolderifyError::General(GeneralError {
+                message: "Could not write the `.directory` entry".into(),
+            }));
+        }
+
+        Ok(())
+    }
+}
+
+const FREEDESKTOP_FOLDER_ICON_FILE_NAME: &str = ".folder.png";
+
+// Percent-encodes everything except unreserved characters and `/`, as expected by `gio`.
+pub fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+// Sets `Icon=` in the `[Desktop Entry]` group, keeping any other existing entries.
+pub fn directory_entry_with_icon(existing: &str, icon: &str) -> String {
+    let icon_line = format!("Icon={}", icon);
+    let mut lines = Vec::<String>::new();
+    let mut in_desktop_entry = false;
+    let mut found_desktop_entry = false;
+    let mut wrote_icon = false;
+    for line in existing.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            if in_desktop_entry && !wrote_icon {
+                lines.push(icon_line.clone());
+                wrote_icon = true;
+            }
+            in_desktop_entry = trimmed == "[Desktop Entry]";
+            found_desktop_entry |= in_desktop_entry;
+        } else if in_desktop_entry && trimmed.starts_with("Icon=") {
+            if !wrote_icon {
+                lines.push(icon_line.clone());
+                wrote_icon = true;
+            }
+            continue;
+        }
+        lines.push(line.to_owned());
+    }
+    if in_desktop_entry && !wrote_icon {
+        lines.push(icon_line.clone());
+    }
+    if !found_desktop_entry {
+        lines.push("[Desktop Entry]".into());
+        lines.push(icon_line);
+    }
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    contents
 }
 
 pub fn escape_path_for_applescript(path: &str) -> String {
     // Newlines don't need to be escaped.
     path.replace('\\', "\\\\").replace('\"', "\\\"")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::icon_conversion::{directory_entry_with_icon, file_uri, IconResolution};
+
+    #[test]
+    fn test_file_uri() {
+        assert_eq!(
+            file_uri(Path::new("/home/me/My Project/.folder.png")),
+            "file:///home/me/My%20Project/.folder.png"
+        );
+    }
+
+    // (offset_y, bottom bezel spread, bottom bezel page_y, bottom bezel alpha, badge size, badge margin)
+    type DrawingParameters = (i32, u32, i32, f32, u32, i32);
+
+    fn drawing_parameters(resolution: &IconResolution) -> DrawingParameters {
+        let blur = resolution.bottom_bezel_blur_down();
+        (
+            resolution.offset_y(),
+            blur.spread_px,
+            blur.page_y,
+            resolution.bottom_bezel_alpha(),
+            resolution.badge_size(),
+            resolution.badge_margin(),
+        )
+    }
+
+    #[test]
+    fn test_iconset_resolution_parameters() {
+        let parameters: Vec<(String, DrawingParameters)> = IconResolution::values()
+            .iter()
+            .map(|resolution| (resolution.to_string(), drawing_parameters(resolution)))
+            .collect();
+        assert_eq!(
+            parameters,
+            vec![
+                ("512x512@2x".into(), (-48, 2, 1, 0.75, 224, 12)),
+                ("512x512".into(), (-24, 2, 1, 0.75, 112, 6)),
+                ("256x256@2x".into(), (-24, 2, 1, 0.75, 112, 6)),
+                ("256x256".into(), (-12, 2, 1, 0.6, 56, 3)),
+                ("128x128@2x".into(), (-12, 2, 1, 0.6, 56, 3)),
+                ("128x128".into(), (-6, 2, 1, 0.6, 28, 2)),
+                ("32x32@2x".into(), (-3, 2, 1, 0.6, 16, 0)),
+                ("32x32".into(), (-2, 2, 1, 0.35, 8, 0)),
+                ("16x16@2x".into(), (-2, 2, 1, 0.35, 16, 0)),
+                ("16x16".into(), (-2, 1, 0, 0.5, 8, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_resolution_at_iconset_sizes() {
+        // Custom sizes match the `.iconset` resolution with the same pixel size exactly.
+        for resolution in IconResolution::values() {
+            let custom = IconResolution::Custom(resolution.size());
+            let rendered = custom.rendered_resolution();
+            assert_eq!(rendered.size(), resolution.size());
+            assert_eq!(drawing_parameters(&custom), drawing_parameters(&rendered));
+        }
+    }
+
+    #[test]
+    fn test_badge_size_for_count() {
+        for (resolution, badge_size) in [
+            (IconResolution::NonRetina16, 5),
+            (IconResolution::Retina16, 10),
+            (IconResolution::NonRetina32, 8),
+            (IconResolution::Custom(24), 8),
+        ] {
+            assert_eq!(resolution.badge_size_for_count(1), resolution.badge_size());
+            assert_eq!(resolution.badge_size_for_count(2), badge_size);
+            assert!(resolution.badge_size_for_count(3) <= resolution.badge_size());
+        }
+        let large = IconResolution::Retina32;
+        assert_eq!(large.badge_size_for_count(2), large.badge_size());
+    }
+
+    #[test]
+    fn test_custom_resolution() {
+        assert_eq!(
+            IconResolution::from_pixel_size(32),
+            Some(IconResolution::NonRetina32)
+        );
+        assert_eq!(
+            IconResolution::from_pixel_size(64),
+            Some(IconResolution::Retina32)
+        );
+        assert_eq!(
+            IconResolution::from_pixel_size(1024),
+            Some(IconResolution::Retina512)
+        );
+        assert_eq!(
+            IconResolution::Custom(64).rendered_resolution(),
+            IconResolution::Retina32
+        );
+        assert_eq!(IconResolution::from_pixel_size(8), None);
+        assert_eq!(IconResolution::from_pixel_size(2048), None);
+
+        // Halfway between `32x32` and `32x32@2x`.
+        let resolution = IconResolution::Custom(48);
+        assert_eq!(resolution.to_string(), "48x48");
+        assert_eq!(resolution.icon_file(), "icon_48x48.png");
+        assert_eq!(resolution.template_resolution(), IconResolution::Retina32);
+        assert_eq!(resolution.rendered_resolution(), resolution);
+        assert_eq!(resolution.offset_y(), -3);
+        assert_eq!(resolution.bottom_bezel_blur_down().spread_px, 2);
+        assert!((resolution.bottom_bezel_alpha() - 0.475).abs() < 1e-6);
+        assert_eq!(resolution.badge_size(), 12);
+        assert_eq!(resolution.badge_margin(), 0);
+
+        // Halfway between `32x32@2x` and `128x128`.
+        let resolution = IconResolution::Custom(96);
+        assert_eq!(
+            resolution.template_resolution(),
+            IconResolution::NonRetina128
+        );
+        assert_eq!(resolution.offset_y(), -5);
+        assert_eq!(resolution.badge_size(), 22);
+        assert_eq!(resolution.badge_margin(), 1);
+    }
+
+    #[test]
+    fn test_directory_entry_with_icon() {
+        assert_eq!(
+            directory_entry_with_icon("", "/a.png"),
+            "[Desktop Entry]\nIcon=/a.png\n"
+        );
+        assert_eq!(
+            directory_entry_with_icon(
+                "[Desktop Entry]\nIcon=folder-red\nName=x\n[Other]\nIcon=keep\n",
+                "/a.png"
+            ),
+            "[Desktop Entry]\nIcon=/a.png\nName=x\n[Other]\nIcon=keep\n"
+        );
+        assert_eq!(
+            directory_entry_with_icon("[Dolphin]\nViewMode=1\n", "/a.png"),
+            "[Dolphin]\nViewMode=1\n[Desktop Entry]\nIcon=/a.png\n"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use std::path::{Path, PathBuf};
+
+use crate::icon_conversion::IconResolution;
+
+// https://specifications.freedesktop.org/icon-theme-spec/latest/
+const ICON_THEME_CONTEXT_DIR: &str = "places";
+const INDEX_THEME_FILE_NAME: &str = "index.theme";
+/// Common sizes in Linux desktops (in addition to the `.iconset` sizes).
+const EXTRA_SIZES: [u32; 3] = [24, 48, 64];
+
+/// All resolutions in the theme, from small to large.
+pub fn resolutions() -> Vec<IconResolution> {
+    let mut resolutions: Vec<IconResolution> = IconResolution::values()
+        .into_iter()
+        .chain(EXTRA_SIZES.map(IconResolution::Custom))
+        .collect();
+    resolutions.sort_by_key(|resolution| (resolution.base_size(), resolution.scale()));
+    resolutions
+}
+
+/// The `hicolor`-style directory for a resolution, e.g. `16x16/places` or `16x16@2/places`.
+pub fn resolution_dir(resolution: &IconResolution) -> String {
+    let base_size = resolution.base_size();
+    match resolution.scale() {
+        1 => format!("{}x{}/{}", base_size, base_size, ICON_THEME_CONTEXT_DIR),
+        scale => format!(
+            "{}x{}@{}/{}",
+            base_size, base_size, scale, ICON_THEME_CONTEXT_DIR
+        ),
+    }
+}
+
+pub fn icon_path(theme_dir: &Path, resolution: &IconResolution, icon_name: &str) -> PathBuf {
+    theme_dir
+        .join(resolution_dir(resolution))
+        .join(format!("{}.png", icon_name))
+}
+
+pub fn index_theme_path(theme_dir: &Path) -> PathBuf {
+    theme_dir.join(INDEX_THEME_FILE_NAME)
+}
+
+/// Only lists fixed sizes, so that icon lookups use the size that was rendered for them. There is
+/// no `scalable` directory (even for an `.svg` mask), since the folder templates are raster images:
+/// a scalable icon would be a bitmap that is scaled down instead.
+pub fn index_theme(theme_name: &str) -> String {
+    let resolutions = resolutions();
+    let directories: Vec<String> = resolutions.iter().map(resolution_dir).collect();
+
+    let mut contents = format!(
+        "[Icon Theme]\nName={}\nComment=Folder icons generated by folderify\nInherits=hicolor\nDirectories={}\n",
+        theme_name,
+        directories.join(",")
+    );
+    for resolution in &resolutions {
+        contents.push_str(&format!(
+            "\n[{}]\nSize={}\nScale={}\nContext=Places\nType=Fixed\n",
+            resolution_dir(resolution),
+            resolution.base_size(),
+            resolution.scale()
+        ));
+    }
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        icon_conversion::IconResolution,
+        icon_theme::{resolution_dir, resolutions},
+    };
+
+    #[test]
+    fn test_resolution_dir() {
+        assert_eq!(resolution_dir(&IconResolution::NonRetina16), "16x16/places");
+        assert_eq!(resolution_dir(&IconResolution::Retina32), "32x32@2/places");
+        assert_eq!(resolution_dir(&IconResolution::Custom(48)), "48x48/places");
+    }
+
+    #[test]
+    fn test_resolutions() {
+        let dirs: Vec<String> = resolutions().iter().map(resolution_dir).collect();
+        assert_eq!(
+            dirs,
+            vec![
+                "16x16/places",
+                "16x16@2/places",
+                "24x24/places",
+                "32x32/places",
+                "32x32@2/places",
+                "48x48/places",
+                "64x64/places",
+                "128x128/places",
+                "128x128@2/places",
+                "256x256/places",
+                "256x256@2/places",
+                "512x512/places",
+                "512x512@2/places",
+            ]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use mktemp::Temp;
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM},
+    iterator::Signals,
+};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static CHILD_PROCESSES: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+static TEMP_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// On Ctrl-C (or `SIGTERM`/`SIGHUP`), stops all child processes (e.g. `magick`) and removes
+/// temp dirs (including partial outputs) before exiting.
+pub fn handle_interrupts() {
+    let mut signals = match Signals::new([SIGINT, SIGTERM, SIGHUP]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            eprintln!("Warning: could not handle interrupts: {}", e);
+            return;
+        }
+    };
+    thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+            for pid in CHILD_PROCESSES.lock().unwrap().iter() {
+                kill(*pid);
+            }
+            for temp_dir in TEMP_DIRS.lock().unwrap().iter() {
+                let _ = fs::remove_dir_all(temp_dir);
+            }
+            exit(128 + signal);
+        }
+    });
+}
+
+fn kill(pid: u32) {
+    // SAFETY: `kill` has no memory safety requirements.
+    unsafe {
+        libc::kill(pid as libc::pid_t, libc::SIGTERM);
+    }
+}
+
+/// Keeps track of a child process while it is running, so that it can be stopped on interrupt.
+pub struct ChildProcess {
+    pid: u32,
+}
+
+impl ChildProcess {
+    pub fn register(pid: u32) -> ChildProcess {
+        let mut child_processes = CHILD_PROCESSES.lock().unwrap();
+        // The process may have been spawned after the other children were stopped.
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            kill(pid);
+        }
+        child_processes.push(pid);
+        ChildProcess { pid }
+    }
+}
+
+impl Drop for ChildProcess {
+    fn drop(&mut self) {
+        CHILD_PROCESSES
+            .lock()
+            .unwrap()
+            .retain(|pid| *pid != self.pid);
+    }
+}
+
+/// A temp dir that is removed when it is dropped, or on interrupt.
+#[derive(Debug)]
+pub struct TempDir {
+    // Only `None` after `keep()`.
+    temp: Option<Temp>,
+}
+
+impl TempDir {
+    pub fn new() -> std::io::Result<TempDir> {
+        Ok(Self::register(Temp::new_dir()?))
+    }
+
+    /// Creates the temp dir inside `dir`, e.g. so that its contents can be moved into `dir`
+    /// without copying.
+    pub fn new_in(dir: &Path) -> std::io::Result<TempDir> {
+        Ok(Self::register(Temp::new_dir_in(dir)?))
+    }
+
+    fn register(temp: Temp) -> TempDir {
+        TEMP_DIRS.lock().unwrap().push(temp.to_path_buf());
+        TempDir { temp: Some(temp) }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.temp.as_ref().unwrap().as_path()
+    }
+
+    /// Keeps the temp dir (e.g. for debugging) instead of removing it.
+    pub fn keep(mut self) -> PathBuf {
+        self.unregister();
+        self.temp.take().unwrap().release()
+    }
+
+    fn unregister(&self) {
+        let path = self.path().to_owned();
+        TEMP_DIRS
+            .lock()
+            .unwrap()
+            .retain(|temp_dir| *temp_dir != path);
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if self.temp.is_some() {
+            self.unregister();
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use rayon::{
+    iter::{IntoParallelIterator, ParallelIterator},
+    ThreadPoolBuilder,
+};
+
+use crate::error::{FolderifyError, GeneralError};
+
+pub type Job<T> = Box<dyn FnOnce() -> Result<T, FolderifyError> + Send>;
+
+/// Sets the number of jobs (e.g. icon sizes) that run at the same time, for the whole process.
+/// The jobs of all renders (e.g. concurrent requests to `folderify serve`) share this limit, so
+/// that they don't multiply the number of `magick` processes. Defaults to the number of CPUs.
+pub fn set_max_jobs(max_jobs: Option<usize>) {
+    if let Some(max_jobs) = max_jobs {
+        ThreadPoolBuilder::new()
+            .num_threads(max_jobs)
+            .thread_name(|index| format!("folderify-job-{}", index))
+            .build_global()
+            .expect("The job pool can only be configured once.");
+    }
+}
+
+/// Runs the jobs on the shared (work-stealing) pool, and returns their results in the original
+/// order (regardless of the order in which they finish).
+pub fn run_jobs<T: Send>(jobs: Vec<Job<T>>) -> Vec<Result<T, FolderifyError>> {
+    jobs.into_par_iter()
+        .map(|job| {
+            panic::catch_unwind(AssertUnwindSafe(job)).unwrap_or_else(|_| {
+                Err(FolderifyError::General(GeneralError {
+                    message: "An icon conversion job panicked.".into(),
+                }))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use crate::jobs::{run_jobs, Job};
+
+    #[test]
+    fn test_run_jobs() {
+        let jobs: Vec<Job<u64>> = (0..8u64)
+            .map(|i| -> Job<u64> {
+                Box::new(move || {
+                    // Later jobs finish first.
+                    thread::sleep(Duration::from_millis(8 - i));
+                    Ok(i)
+                })
+            })
+            .collect();
+        let results: Vec<u64> = run_jobs(jobs)
+            .into_iter()
+            .map(|result| result.unwrap())
+            .collect();
+        assert_eq!(results, (0..8).collect::<Vec<u64>>());
+
+        let results = run_jobs(vec![
+            Box::new(|| Ok(1u64)) as Job<u64>,
+            Box::new(|| panic!("oops")),
+        ]);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use serde_json::{Map, Value};
+
+/// Converts JSON options (named after command-line flags) to flags:
+///
+/// - `true` → `--flag`
+/// - `false` → (nothing)
+/// - a string or number → `--flag=value`
+/// - an array → the flag once for each value
+///
+/// Flags are emitted in alphabetical order, because JSON objects don't keep the order of their
+/// fields. Flags that depend on their order relative to each other can't be combined.
+pub fn flags_from_json(
+    fields: &Map<String, Value>,
+    is_supported: impl Fn(&str) -> bool,
+) -> Result<Vec<String>, String> {
+    let value_count = |name: &str| match fields.get(name) {
+        None | Some(Value::Bool(false)) => 0,
+        Some(Value::Array(values)) => values.len(),
+        Some(_) => 1,
+    };
+    // Each `--size` applies to the preceding `--output-png`.
+    if value_count("output-png") > 1 && value_count("size") > 0 {
+        return Err("Multiple `output-png` values cannot be combined with `size`.".into());
+    }
+    // Badges are drawn in order.
+    if value_count("badge") > 0 && value_count("badge-image") > 0 {
+        return Err("`badge` cannot be combined with `badge-image`.".into());
+    }
+
+    let mut flags = Vec::<String>::new();
+    for (name, value) in fields {
+        if !is_supported(name) {
+            return Err(format!("Unsupported option: {}", name));
+        }
+        let values = match value {
+            Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                Value::Bool(true) => flags.push(format!("--{}", name)),
+                Value::Bool(false) => {}
+                Value::String(value) => flags.push(format!("--{}={}", name, value)),
+                Value::Number(value) => flags.push(format!("--{}={}", name, value)),
+                _ => return Err(format!("Invalid value for option: {}", name)),
+            }
+        }
+    }
+    Ok(flags)
+}
+
+/// Whether the flags render text or an emoji (in which case a mask file would be parsed as the
+/// target).
+pub fn has_text_mask(flags: &[String]) -> bool {
+    flags
+        .iter()
+        .any(|flag| flag.starts_with("--text=") || flag.starts_with("--emoji="))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::json_flags::flags_from_json;
+
+    #[test]
+    fn test_flags_from_json() {
+        let flags =
+            |fields: serde_json::Value| flags_from_json(fields.as_object().unwrap(), |_| true);
+        assert_eq!(
+            flags(json!({"size": "48", "output-png": "a.png", "no-trim": true, "verbose": false})),
+            Ok(vec![
+                "--no-trim".into(),
+                "--output-png=a.png".into(),
+                "--size=48".into(),
+            ])
+        );
+        assert_eq!(
+            flags(json!({"output-png": ["a.png", "b.png"]})),
+            Ok(vec![
+                "--output-png=a.png".into(),
+                "--output-png=b.png".into()
+            ])
+        );
+        assert!(flags(json!({"output-png": ["a.png", "b.png"], "size": ["48", "64"]})).is_err());
+        assert!(flags(json!({"badge": "locked", "badge-image": "logo.png"})).is_err());
+        assert!(flags(json!({"badge": {"name": "locked"}})).is_err());
+        assert!(flags_from_json(json!({"jobs": 2}).as_object().unwrap(), |_| false).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::args::FolderStyle;
+
+pub struct MacOSRelease {
+    /// Matches this version and any of its subversions, e.g. "14" matches "14.2.1".
+    pub version: &'static str,
+    pub name: &'static str,
+    /// Matched case-insensitively, in addition to the `version`.
+    pub aliases: &'static [&'static str],
+    /// `None` for versions that are only supported by folderify v2.
+    pub folder_style: Option<FolderStyle>,
+    /// Set for a release that is not final yet, which prints a warning when it is used.
+    pub beta: bool,
+}
+
+impl MacOSRelease {
+    pub fn beta_warning(&self) -> Option<String> {
+        self.beta.then(|| {
+            format!(
+                "Warning: macOS {} is still in beta. The icon may not match the final macOS {} release.",
+                self.name, self.version
+            )
+        })
+    }
+
+    /// The line for `folderify styles`, e.g. `  macOS 26     tahoe`.
+    pub fn styles_line(&self) -> String {
+        format!(
+            "  macOS {:<6} {}{}",
+            self.version,
+            self.aliases.join(", "),
+            if self.beta { " (beta)" } else { "" }
+        )
+    }
+}
+
+/// Adding a macOS release only requires adding an entry here.
+/// Note: macOS 16 through 25 do not exist.
+pub const MACOS_RELEASES: &[MacOSRelease] = &[
+    release_v2("10.5", "Leopard", &["leopard"]),
+    release_v2("10.6", "Snow Leopard", &["snow-leopard"]),
+    release_v2("10.7", "Lion", &["lion"]),
+    release_v2("10.8", "Mountain Lion", &["mountain-lion"]),
+    release_v2("10.9", "Mavericks", &["mavericks"]),
+    release_v2("10.10", "Yosemite", &["yosemite"]),
+    release_v2("10.11", "El Capitan", &["el-capitan"]),
+    release_v2("10.12", "Sierra", &["sierra"]),
+    release_v2("10.13", "High Sierra", &["high-sierra"]),
+    release_v2("10.14", "Mojave", &["mojave"]),
+    release_v2("10.15", "Catalina", &["catalina"]),
+    release("11", "Big Sur", &["big-sur", "bigsur"], FolderStyle::BigSur),
+    release("12", "Monterey", &["monterey"], FolderStyle::BigSur),
+    release("13", "Ventura", &["ventura"], FolderStyle::BigSur),
+    release("14", "Sonoma", &["sonoma"], FolderStyle::BigSur),
+    release("15", "Sequoia", &["sequoia"], FolderStyle::BigSur),
+    release("26", "Tahoe", &["tahoe"], FolderStyle::Tahoe),
+];
+
+/// Used for versions that are not in `MACOS_RELEASES` (e.g. "10.16", which macOS 11 reports in some APIs).
+pub const UNKNOWN_VERSION_FOLDER_STYLE: FolderStyle = FolderStyle::BigSur;
+
+const fn release(
+    version: &'static str,
+    name: &'static str,
+    aliases: &'static [&'static str],
+    folder_style: FolderStyle,
+) -> MacOSRelease {
+    MacOSRelease {
+        version,
+        name,
+        aliases,
+        folder_style: Some(folder_style),
+        beta: false,
+    }
+}
+
+const fn release_v2(
+    version: &'static str,
+    name: &'static str,
+    aliases: &'static [&'static str],
+) -> MacOSRelease {
+    MacOSRelease {
+        version,
+        name,
+        aliases,
+        folder_style: None,
+        beta: false,
+    }
+}
+
+fn is_version_or_subversion(mac_os: &str, version: &str) -> bool {
+    mac_os == version || mac_os.starts_with(&format!("{}.", version))
+}
+
+pub fn lookup_macos_release(mac_os: &str) -> Option<&'static MacOSRelease> {
+    let mac_os = mac_os.trim();
+    MACOS_RELEASES.iter().find(|release| {
+        is_version_or_subversion(mac_os, release.version)
+            || release
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(mac_os))
+    })
+}
+
+/// The releases that use the given folder style, e.g. "macOS 11 (Big Sur) through macOS 15 (Sequoia)".
+pub fn release_range_description(folder_style: FolderStyle) -> Option<String> {
+    let mut releases = MACOS_RELEASES
+        .iter()
+        .filter(|release| release.folder_style == Some(folder_style));
+    let first = releases.next()?;
+    let description = match releases.next_back() {
+        Some(last) => format!(
+            "macOS {} ({}) through macOS {} ({})",
+            first.version, first.name, last.version, last.name
+        ),
+        None => format!("macOS {} ({})", first.version, first.name),
+    };
+    Some(description)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        args::FolderStyle,
+        macos_versions::{lookup_macos_release, release, release_range_description, MacOSRelease},
+    };
+
+    fn folder_style(mac_os: &str) -> Option<Option<FolderStyle>> {
+        lookup_macos_release(mac_os).map(|release| release.folder_style)
+    }
+
+    #[test]
+    fn test_lookup_macos_release() {
+        assert_eq!(folder_style("11.0"), Some(Some(FolderStyle::BigSur)));
+        assert_eq!(folder_style("14.2.1"), Some(Some(FolderStyle::BigSur)));
+        assert_eq!(folder_style("15.0\n"), Some(Some(FolderStyle::BigSur)));
+        assert_eq!(folder_style("26"), Some(Some(FolderStyle::Tahoe)));
+        assert_eq!(folder_style("26.1"), Some(Some(FolderStyle::Tahoe)));
+        assert_eq!(folder_style("10.5"), Some(None));
+        assert_eq!(folder_style("10.15.7"), Some(None));
+        assert_eq!(folder_style("10.16"), None);
+        assert_eq!(folder_style("10.1"), None);
+        assert_eq!(folder_style("99.0"), None);
+    }
+
+    #[test]
+    fn test_lookup_macos_release_alias() {
+        assert_eq!(folder_style("sonoma"), Some(Some(FolderStyle::BigSur)));
+        assert_eq!(folder_style("Tahoe"), Some(Some(FolderStyle::Tahoe)));
+        assert_eq!(folder_style("big-sur"), Some(Some(FolderStyle::BigSur)));
+        assert_eq!(folder_style("mountain-lion"), Some(None));
+    }
+
+    #[test]
+    fn test_beta() {
+        let tahoe = lookup_macos_release("tahoe").unwrap();
+        assert_eq!(tahoe.beta_warning(), None);
+        assert_eq!(tahoe.styles_line(), "  macOS 26     tahoe");
+
+        let beta = MacOSRelease {
+            beta: true,
+            ..release("27", "Next", &["next"], FolderStyle::Tahoe)
+        };
+        assert_eq!(
+            beta.beta_warning().unwrap(),
+            "Warning: macOS Next is still in beta. The icon may not match the final macOS 27 release."
+        );
+        assert_eq!(beta.styles_line(), "  macOS 27     next (beta)");
+    }
+
+    #[test]
+    fn test_release_range_description() {
+        assert_eq!(
+            release_range_description(FolderStyle::BigSur).unwrap(),
+            "macOS 11 (Big Sur) through macOS 15 (Sequoia)"
+        );
+        assert_eq!(
+            release_range_description(FolderStyle::Tahoe).unwrap(),
+            "macOS 26 (Tahoe)"
+        );
+        assert_eq!(release_range_description(FolderStyle::Adwaita), None);
+    }
+}
@@ -73,6 +73,26 @@ impl CommandArgs {
         self.push("Center");
     }
 
+    pub fn gravity(&mut self, gravity: &Gravity) {
+        self.push("-gravity");
+        self.push(match gravity {
+            Gravity::North => "North",
+            Gravity::South => "South",
+            Gravity::West => "West",
+            Gravity::East => "East",
+            Gravity::NorthWest => "NorthWest",
+            Gravity::SouthWest => "SouthWest",
+            Gravity::SouthEast => "SouthEast",
+            Gravity::NorthEast => "NorthEast",
+            Gravity::Center => "Center",
+        });
+    }
+
+    pub fn geometry(&mut self, offset: &Offset) {
+        self.push("-geometry");
+        self.push(&offset.to_string());
+    }
+
     pub fn fill_colorize(&mut self, fill_color: &RGBColor) {
         self.push("-fill");
         self.push(&fill_color.to_string());
@@ -80,6 +100,26 @@ impl CommandArgs {
         self.push("100, 100, 100");
     }
 
+    pub fn fill(&mut self, fill_color: &RGBColor) {
+        self.push("-fill");
+        self.push(&fill_color.to_string());
+    }
+
+    pub fn font(&mut self, font: &Path) {
+        self.push("-font");
+        self.push_path(font);
+    }
+
+    pub fn pointsize(&mut self, pointsize: u32) {
+        self.push("-pointsize");
+        self.push(&pointsize.to_string());
+    }
+
+    pub fn size(&mut self, dimensions: &Dimensions) {
+        self.push("-size");
+        self.push(&dimensions.to_string());
+    }
+
     pub fn opacity(&mut self, alpha: f32) {
         self.push("-channel");
         self.push("Alpha");
@@ -140,6 +180,18 @@ pub struct BlurDown {
     pub page_y: i32,
 }
 
+pub enum Gravity {
+    North,
+    South,
+    West,
+    East,
+    NorthWest,
+    SouthWest,
+    SouthEast,
+    NorthEast,
+    Center,
+}
+
 #[allow(non_camel_case_types)] // Match ImageMagick args
 pub enum CompositingOperation {
     Dst_In,
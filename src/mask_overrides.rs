@@ -0,0 +1,101 @@
+use std::path::{Path, PathBuf};
+
+use crate::icon_conversion::IconResolution;
+
+/// A hand-tuned mask that is used instead of the main mask for a single resolution.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaskOverride {
+    pub resolution: IconResolution,
+    pub path: PathBuf,
+}
+
+/// File name of a mask for icons of the given pixel size, e.g. `mask@16.png` (for `16x16`) or
+/// `mask@32.png` (for `16x16@2x` and `32x32`).
+fn sized_file_name(stem: &str, extension: &str, size: u32) -> String {
+    format!("{}@{}.{}", stem, size, extension)
+}
+
+/// Finds overrides for the mask:
+///
+/// - `icon_<resolution>.png` files in `mask_iconset` (if specified).
+/// - Files next to the mask named by pixel size, e.g. `mask@16.png` next to `mask.png`.
+///
+/// Files in `mask_iconset` take precedence.
+pub fn find_mask_overrides(mask_path: &Path, mask_iconset: Option<&Path>) -> Vec<MaskOverride> {
+    let mut mask_overrides = Vec::<MaskOverride>::new();
+    let stem = mask_path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut extensions = vec!["png".to_owned()];
+    if let Some(extension) = mask_path.extension() {
+        let extension = extension.to_string_lossy().to_string();
+        if extension != "png" {
+            extensions.insert(0, extension);
+        }
+    }
+    for resolution in IconResolution::values() {
+        let from_iconset = mask_iconset
+            .map(|mask_iconset| mask_iconset.join(resolution.icon_file()))
+            .filter(|path| path.is_file());
+        let from_sibling = || {
+            extensions
+                .iter()
+                .map(|extension| {
+                    mask_path.with_file_name(sized_file_name(&stem, extension, resolution.size()))
+                })
+                .find(|path| path.is_file())
+        };
+        if let Some(path) = from_iconset.or_else(from_sibling) {
+            mask_overrides.push(MaskOverride { resolution, path });
+        }
+    }
+    mask_overrides
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir, write};
+
+    use mktemp::Temp;
+
+    use crate::{
+        icon_conversion::IconResolution,
+        mask_overrides::{find_mask_overrides, MaskOverride},
+    };
+
+    #[test]
+    fn test_find_mask_overrides() {
+        let dir = Temp::new_dir().unwrap();
+        let mask_path = dir.join("mask.png");
+        write(&mask_path, b"").unwrap();
+        write(dir.join("mask@16.png"), b"").unwrap();
+        write(dir.join("mask@32.png"), b"").unwrap();
+        write(dir.join("other@16.png"), b"").unwrap();
+        let mask_iconset = dir.join("hinted.iconset");
+        create_dir(&mask_iconset).unwrap();
+        write(mask_iconset.join("icon_32x32.png"), b"").unwrap();
+
+        assert_eq!(
+            find_mask_overrides(&mask_path, None),
+            vec![
+                MaskOverride {
+                    resolution: IconResolution::NonRetina32,
+                    path: dir.join("mask@32.png"),
+                },
+                MaskOverride {
+                    resolution: IconResolution::Retina16,
+                    path: dir.join("mask@32.png"),
+                },
+                MaskOverride {
+                    resolution: IconResolution::NonRetina16,
+                    path: dir.join("mask@16.png"),
+                },
+            ]
+        );
+        assert_eq!(
+            find_mask_overrides(&mask_path, Some(&mask_iconset))[0],
+            MaskOverride {
+                resolution: IconResolution::NonRetina32,
+                path: mask_iconset.join("icon_32x32.png"),
+            }
+        );
+    }
+}
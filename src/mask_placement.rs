@@ -0,0 +1,188 @@
+use crate::{
+    args::MaskAnchor,
+    icon_conversion::IconResolution,
+    magick::Gravity,
+    primitives::{Dimensions, Offset},
+};
+
+/// Placement units are relative to the largest icon size.
+const PLACEMENT_ICON_SIZE: u32 = 1024;
+
+/// The part of the folder front face (for all folder styles) that a mask can be placed on, in
+/// placement units: (left, top, right, bottom).
+const FRONT_FACE_SAFE_AREA: (f64, f64, f64, f64) = (72.0, 280.0, 952.0, 860.0);
+/// Small sizes are rounded to whole pixels (e.g. the default mask area at 16x16 extends 0.6px past
+/// the safe area).
+const ROUNDING_TOLERANCE_PX: f64 = 1.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaskPlacement {
+    pub scale: f64,
+    /// Positive values move the mask right.
+    pub offset_x: i32,
+    /// Positive values move the mask down.
+    pub offset_y: i32,
+    pub anchor: MaskAnchor,
+}
+
+impl Default for MaskPlacement {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            offset_x: 0,
+            offset_y: 0,
+            anchor: MaskAnchor::Center,
+        }
+    }
+}
+
+impl MaskPlacement {
+    /// The area that the mask is fitted into.
+    pub fn mask_dimensions(&self, resolution: &IconResolution) -> Dimensions {
+        let size = resolution.size();
+        Dimensions {
+            width: scale_dimension(size * 3 / 4, self.scale),
+            height: scale_dimension(size / 2, self.scale),
+        }
+    }
+
+    /// The `-extent` offset that places the mask area on the icon.
+    pub fn extent_offset(&self, resolution: &IconResolution) -> Offset {
+        let size = resolution.size();
+        Offset {
+            x: -scale_offset(self.offset_x, size),
+            y: resolution.offset_y() - scale_offset(self.offset_y, size),
+        }
+    }
+
+    pub fn gravity(&self) -> Gravity {
+        match self.anchor {
+            MaskAnchor::Center => Gravity::Center,
+            MaskAnchor::Top => Gravity::North,
+            MaskAnchor::Bottom => Gravity::South,
+            MaskAnchor::Left => Gravity::West,
+            MaskAnchor::Right => Gravity::East,
+            MaskAnchor::TopLeft => Gravity::NorthWest,
+            MaskAnchor::TopRight => Gravity::NorthEast,
+            MaskAnchor::BottomLeft => Gravity::SouthWest,
+            MaskAnchor::BottomRight => Gravity::SouthEast,
+        }
+    }
+
+    /// Checks that the mask area is not empty and stays within the folder front face, at every
+    /// icon size (since rounding differs between sizes).
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.scale.is_finite() || self.scale <= 0.0 {
+            return Err(format!("mask scale must be positive: {}", self.scale));
+        }
+        for resolution in IconResolution::values() {
+            let size = resolution.size();
+            let dimensions = self.mask_dimensions(&resolution);
+            if dimensions.width == 0 || dimensions.height == 0 {
+                return Err(format!(
+                    "mask scale is too small: {} (the mask would be empty at {}).",
+                    self.scale, resolution
+                ));
+            }
+            let offset = self.extent_offset(&resolution);
+            let to_pixels =
+                |placement_units: f64| placement_units * size as f64 / PLACEMENT_ICON_SIZE as f64;
+            let center_x = size as f64 / 2.0 - offset.x as f64;
+            let center_y = size as f64 / 2.0 - offset.y as f64;
+            let (left, top, right, bottom) = FRONT_FACE_SAFE_AREA;
+            let half_width = dimensions.width as f64 / 2.0;
+            let half_height = dimensions.height as f64 / 2.0;
+            if center_x - half_width < to_pixels(left) - ROUNDING_TOLERANCE_PX
+                || center_y - half_height < to_pixels(top) - ROUNDING_TOLERANCE_PX
+                || center_x + half_width > to_pixels(right) + ROUNDING_TOLERANCE_PX
+                || center_y + half_height > to_pixels(bottom) + ROUNDING_TOLERANCE_PX
+            {
+                return Err(format!(
+                    "the mask would extend past the front of the folder at {} (mask area: {}x{} at ({}, {}), allowed area: ({}, {}) to ({}, {})). Try a smaller `--mask-scale` or offset.",
+                    resolution,
+                    dimensions.width,
+                    dimensions.height,
+                    center_x - half_width,
+                    center_y - half_height,
+                    to_pixels(left),
+                    to_pixels(top),
+                    to_pixels(right),
+                    to_pixels(bottom),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn scale_dimension(dimension: u32, scale: f64) -> u32 {
+    (dimension as f64 * scale).round() as u32
+}
+
+fn scale_offset(offset: i32, icon_size: u32) -> i32 {
+    (offset as f64 * icon_size as f64 / PLACEMENT_ICON_SIZE as f64).round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{args::MaskAnchor, icon_conversion::IconResolution, mask_placement::MaskPlacement};
+
+    #[test]
+    fn test_default_placement() {
+        let placement = MaskPlacement::default();
+        for resolution in IconResolution::values() {
+            let size = resolution.size();
+            let dimensions = placement.mask_dimensions(&resolution);
+            assert_eq!(dimensions.width, size * 3 / 4);
+            assert_eq!(dimensions.height, size / 2);
+            let offset = placement.extent_offset(&resolution);
+            assert_eq!(offset.x, 0);
+            assert_eq!(offset.y, resolution.offset_y());
+        }
+        assert_eq!(placement.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_scaled_placement() {
+        let placement = MaskPlacement {
+            scale: 0.5,
+            offset_x: 100,
+            offset_y: -20,
+            anchor: MaskAnchor::Top,
+        };
+        let dimensions = placement.mask_dimensions(&IconResolution::NonRetina128);
+        assert_eq!((dimensions.width, dimensions.height), (48, 32));
+        let offset = placement.extent_offset(&IconResolution::NonRetina128);
+        assert_eq!((offset.x, offset.y), (-13, -6 + 3));
+        assert_eq!(placement.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate() {
+        let too_big = MaskPlacement {
+            scale: 1.5,
+            ..Default::default()
+        };
+        assert!(too_big.validate().is_err());
+        let too_far_right = MaskPlacement {
+            offset_x: 100,
+            ..Default::default()
+        };
+        assert!(too_far_right.validate().is_err());
+        let not_positive = MaskPlacement {
+            scale: 0.0,
+            ..Default::default()
+        };
+        assert!(not_positive.validate().is_err());
+        let too_small = MaskPlacement {
+            scale: 0.01,
+            ..Default::default()
+        };
+        assert!(too_small.validate().is_err());
+        let smallest = MaskPlacement {
+            scale: 0.0625,
+            ..Default::default()
+        };
+        assert_eq!(smallest.validate(), Ok(()));
+    }
+}
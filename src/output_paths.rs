@@ -1,10 +1,88 @@
-use std::{fs::create_dir_all, path::PathBuf};
+use std::{
+    fs::{self, create_dir_all},
+    path::{Path, PathBuf},
+};
 
-use crate::{args::Options, icon_conversion::WorkingDir};
+use crate::{
+    args::{IconVariant, Options},
+    error::{FolderifyError, GeneralError},
+    icon_conversion::WorkingDir,
+    interrupt::TempDir,
+    status,
+};
 
 pub(crate) struct FinalOutputPaths {
+    pub variant: IconVariant,
     pub iconset_dir: PathBuf,
     pub icns_path: PathBuf,
+    staged_outputs: Vec<StagedOutput>,
+}
+
+impl FinalOutputPaths {
+    /// Moves the outputs into place, once they have been written completely.
+    pub fn commit(&self) -> Result<(), FolderifyError> {
+        for staged_output in &self.staged_outputs {
+            staged_output.commit()?;
+        }
+        Ok(())
+    }
+
+    /// The path where `path` ends up after `commit()`.
+    pub fn committed_path(&self, path: &Path) -> PathBuf {
+        self.staged_outputs
+            .iter()
+            .find(|staged_output| staged_output.staged_path == path)
+            .map(|staged_output| staged_output.final_path.to_owned())
+            .unwrap_or_else(|| path.to_owned())
+    }
+}
+
+/// An output that is written next to its final path (so that an interrupted render doesn't leave
+/// a partial output behind), and then renamed into place.
+struct StagedOutput {
+    // Removes the staged output (e.g. on interrupt) unless it has been renamed into place.
+    _staging_dir: TempDir,
+    staged_path: PathBuf,
+    final_path: PathBuf,
+}
+
+impl StagedOutput {
+    fn new(final_path: &Path) -> Result<StagedOutput, FolderifyError> {
+        let could_not_create = || {
+            FolderifyError::General(GeneralError {
+                message: format!("Could not create the output: {}", final_path.display()),
+            })
+        };
+        let parent = match final_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        create_dir_all(parent).map_err(|_| could_not_create())?;
+        let staging_dir = TempDir::new_in(parent).map_err(|_| could_not_create())?;
+        let file_name = final_path.file_name().ok_or_else(could_not_create)?;
+        Ok(StagedOutput {
+            staged_path: staging_dir.path().join(file_name),
+            _staging_dir: staging_dir,
+            final_path: final_path.to_owned(),
+        })
+    }
+
+    fn commit(&self) -> Result<(), FolderifyError> {
+        // Not every output is written (e.g. an `.icns` file that is only needed for assignment).
+        if !self.staged_path.exists() {
+            return Ok(());
+        }
+        let could_not_write = || {
+            FolderifyError::General(GeneralError {
+                message: format!("Could not write the output: {}", self.final_path.display()),
+            })
+        };
+        // A dir (e.g. `.iconset`) can't be renamed over an existing one.
+        if self.final_path.is_dir() {
+            fs::remove_dir_all(&self.final_path).map_err(|_| could_not_write())?;
+        }
+        fs::rename(&self.staged_path, &self.final_path).map_err(|_| could_not_write())
+    }
 }
 
 // TODO: separate printing from calculation
@@ -26,33 +104,66 @@ impl PotentialOutputPaths {
             &options.output_icns,
         ) {
             (Some(target), output_iconset, output_icns) => {
-                println!(
+                status!(
                     "[{}] => assign to [{}]",
                     options.mask_path.display(),
                     target.display()
                 );
                 Self::alt_outputs(options, &mut output_paths, output_iconset, output_icns);
             }
-            (None, None, None) => {
+            (None, None, None)
+                if options.output_icon_theme.is_none()
+                    && options.output_xcassets.is_none()
+                    && options.output_preview.is_none()
+                    && options.output_animation.is_none()
+                    && options.output_pngs.is_empty() =>
+            {
                 let iconset_dir_value = options.mask_path.with_extension("iconset");
-                let icns_path_value = options.mask_path.with_extension("icns");
-                println!(
-                    "[{}] => [{}]",
-                    options.mask_path.display(),
-                    iconset_dir_value.display()
-                );
-                println!(
-                    "[{}] => [{}]",
-                    options.mask_path.display(),
-                    icns_path_value.display()
-                );
+                Self::print_variant_outputs(options, &iconset_dir_value);
                 output_paths.iconset_dir = Some(iconset_dir_value);
-                output_paths.icns_path = Some(icns_path_value);
+                // `.icns` files can only be created on macOS, so the `.iconset` folder (of `.png`
+                // files) is the only default output for freedesktop systems.
+                if options.set_icon_using.assigns_icns() {
+                    let icns_path_value = options.mask_path.with_extension("icns");
+                    Self::print_icns_outputs(options, &icns_path_value);
+                    output_paths.icns_path = Some(icns_path_value);
+                }
             }
             (None, output_iconset, output_icns) => {
                 Self::alt_outputs(options, &mut output_paths, output_iconset, output_icns);
             }
         }
+        if let Some(output_icon_theme) = &options.output_icon_theme {
+            status!(
+                "[{}] => [{}]",
+                options.mask_path.display(),
+                output_icon_theme.display()
+            );
+        }
+        if let Some(output_xcassets) = &options.output_xcassets {
+            status!(
+                "[{}] => [{}]",
+                options.mask_path.display(),
+                output_xcassets.display()
+            );
+        }
+        if let Some(output_preview) = &options.output_preview {
+            status!(
+                "[{}] => [{}]",
+                options.mask_path.display(),
+                output_preview.display()
+            );
+        }
+        if let Some((output_animation, _)) = &options.output_animation {
+            status!(
+                "[{}] => [{}]",
+                options.mask_path.display(),
+                output_animation.display()
+            );
+        }
+        for (output_png, _) in &options.output_pngs {
+            Self::print_variant_outputs(options, output_png);
+        }
         output_paths
     }
 
@@ -63,41 +174,136 @@ impl PotentialOutputPaths {
         output_icns: &Option<PathBuf>,
     ) {
         if let Some(output_iconset) = output_iconset {
-            println!(
-                "[{}] => [{}]",
-                options.mask_path.display(),
-                output_iconset.display()
-            );
+            Self::print_variant_outputs(options, output_iconset);
             output_targets.iconset_dir = Some(output_iconset.to_owned());
         }
         if let Some(output_icns) = output_icns {
-            println!(
+            Self::print_icns_outputs(options, output_icns);
+            output_targets.icns_path = Some(output_icns.to_owned());
+        }
+    }
+
+    fn print_variant_outputs(options: &Options, path: &Path) {
+        for variant in options.variants() {
+            status!(
                 "[{}] => [{}]",
                 options.mask_path.display(),
-                output_icns.display()
+                with_name_suffix(path, &variant.name_suffix).display()
             );
-            output_targets.icns_path = Some(output_icns.to_owned());
         }
     }
 
-    // This creates the iconset dir if needed (but not the icns path).
-    pub fn finalize(&self, options: &Options, working_dir: &WorkingDir) -> FinalOutputPaths {
+    fn print_icns_outputs(options: &Options, path: &Path) {
+        for variant in options.variants() {
+            if !options.is_embedded_dark_variant(&variant) {
+                status!(
+                    "[{}] => [{}]",
+                    options.mask_path.display(),
+                    with_name_suffix(path, &variant.name_suffix).display()
+                );
+            }
+        }
+    }
+
+    // This creates the iconset dir if needed (but not the icns path). Outputs are staged until
+    // `FinalOutputPaths::commit()`.
+    pub fn finalize(
+        &self,
+        options: &Options,
+        working_dir: &WorkingDir,
+        variant: &IconVariant,
+    ) -> Result<FinalOutputPaths, FolderifyError> {
+        let mut staged_outputs = Vec::<StagedOutput>::new();
+
         let iconset_dir = match &self.iconset_dir {
             Some(iconset_dir) => {
-                create_dir_all(iconset_dir).unwrap(); // TODO
-                iconset_dir.to_owned()
+                let staged_output =
+                    StagedOutput::new(&with_name_suffix(iconset_dir, &variant.name_suffix))?;
+                let iconset_dir = staged_output.staged_path.to_owned();
+                create_dir_all(&iconset_dir).map_err(|_| {
+                    FolderifyError::General(GeneralError {
+                        message: "Could not create iconset dir".into(),
+                    })
+                })?;
+                staged_outputs.push(staged_output);
+                iconset_dir
             }
-            None => working_dir.create_iconset_dir(options).unwrap(),
+            None => working_dir.create_iconset_dir(options, variant)?,
         };
 
+        let working_icns_path = || {
+            with_name_suffix(
+                &working_dir.icon_file_with_extension("icns"),
+                &variant.name_suffix,
+            )
+        };
         let icns_path = match &self.icns_path {
-            Some(icns_path) => icns_path.to_owned(),
-            None => working_dir.icon_file_with_extension("icns"),
+            // Written to stdout after it is created.
+            Some(_) if options.icns_to_stdout() => working_icns_path(),
+            // Only embedded in the light `.icns` file.
+            Some(_) if options.is_embedded_dark_variant(variant) => working_icns_path(),
+            Some(icns_path) => {
+                let staged_output =
+                    StagedOutput::new(&with_name_suffix(icns_path, &variant.name_suffix))?;
+                let icns_path = staged_output.staged_path.to_owned();
+                staged_outputs.push(staged_output);
+                icns_path
+            }
+            None => working_icns_path(),
         };
 
-        FinalOutputPaths {
+        Ok(FinalOutputPaths {
+            variant: variant.clone(),
             iconset_dir,
             icns_path,
-        }
+            staged_outputs,
+        })
+    }
+}
+
+/// Inserts the suffix before the extension, e.g. `mask.icns` => `mask.dark.icns`.
+pub(crate) fn with_name_suffix(path: &Path, name_suffix: &Option<String>) -> PathBuf {
+    let Some(name_suffix) = name_suffix else {
+        return path.to_owned();
+    };
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, name_suffix, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, name_suffix),
+    };
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{interrupt::TempDir, output_paths::StagedOutput};
+
+    #[test]
+    fn test_staged_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let iconset_dir = temp_dir.path().join("icon.iconset");
+        fs::create_dir(&iconset_dir).unwrap();
+        fs::write(iconset_dir.join("stale.png"), "stale").unwrap();
+
+        let staged_output = StagedOutput::new(&iconset_dir).unwrap();
+        fs::create_dir(&staged_output.staged_path).unwrap();
+        fs::write(staged_output.staged_path.join("icon_16x16.png"), "png").unwrap();
+        // Nothing is written to the final path until the output is committed.
+        assert!(iconset_dir.join("stale.png").exists());
+        staged_output.commit().unwrap();
+        drop(staged_output);
+        assert!(!iconset_dir.join("stale.png").exists());
+        assert!(iconset_dir.join("icon_16x16.png").exists());
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        // An output that is never committed is removed.
+        let icns_path = temp_dir.path().join("icon.icns");
+        let staged_output = StagedOutput::new(&icns_path).unwrap();
+        fs::write(&staged_output.staged_path, "icns").unwrap();
+        drop(staged_output);
+        assert!(!icns_path.exists());
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
     }
 }
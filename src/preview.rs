@@ -0,0 +1,210 @@
+use std::{fs, io::Write, path::Path};
+
+use crate::{
+    args::{PreviewOptions, PreviewProtocol},
+    base64,
+    command::{run_command, MAGICK_COMMAND},
+    error::{FolderifyError, GeneralError},
+    magick::CommandArgs,
+    primitives::Dimensions,
+};
+
+// https://sw.kovidgoyal.net/kitty/graphics-protocol/#remote-client
+const KITTY_CHUNK_SIZE: usize = 4096;
+/// Each half block is one pixel wide, so larger icons are scaled down to fit in the terminal.
+const BLOCKS_MAX_WIDTH: u32 = 48;
+/// Less opaque pixels are left empty.
+const BLOCKS_ALPHA_THRESHOLD: u8 = 128;
+
+const SIXEL_TERMS: &[&str] = &["foot", "foot-extra", "mlterm", "yaft-256color"];
+
+/// Detects the terminal graphics support from environment variables (which are mostly passed
+/// through SSH).
+pub fn detect_protocol(env: impl Fn(&str) -> Option<String>) -> PreviewProtocol {
+    let term = env("TERM").unwrap_or_default();
+    let term_program = env("TERM_PROGRAM").unwrap_or_default();
+    if env("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || term == "xterm-ghostty" {
+        return PreviewProtocol::Kitty;
+    }
+    if term_program == "iTerm.app"
+        || term_program == "WezTerm"
+        || env("LC_TERMINAL").as_deref() == Some("iTerm2")
+    {
+        return PreviewProtocol::Iterm2;
+    }
+    if SIXEL_TERMS.contains(&term.as_str()) {
+        return PreviewProtocol::Sixel;
+    }
+    PreviewProtocol::Blocks
+}
+
+pub fn print_preview(
+    iconset_dir: &Path,
+    preview: &PreviewOptions,
+    output: &mut impl Write,
+) -> Result<(), FolderifyError> {
+    let icon_path = iconset_dir.join(preview.resolution.icon_file());
+    let protocol = match preview.protocol {
+        PreviewProtocol::Auto => detect_protocol(|name| std::env::var(name).ok()),
+        protocol => protocol,
+    };
+    let read_icon = || {
+        fs::read(&icon_path).map_err(|_| {
+            FolderifyError::General(GeneralError {
+                message: "Could not read the icon for the preview.".into(),
+            })
+        })
+    };
+    let preview_bytes = match protocol {
+        PreviewProtocol::Kitty => kitty_image(&read_icon()?),
+        PreviewProtocol::Iterm2 => iterm2_image(&read_icon()?),
+        PreviewProtocol::Sixel => {
+            let mut args = CommandArgs::new();
+            args.push_path(&icon_path);
+            args.push("sixel:-");
+            run_command(MAGICK_COMMAND, &args, None)?
+        }
+        PreviewProtocol::Auto | PreviewProtocol::Blocks => {
+            let width = preview.resolution.size().min(BLOCKS_MAX_WIDTH);
+            let mut args = CommandArgs::new();
+            args.push_path(&icon_path);
+            args.resize(&Dimensions::square(width));
+            args.push("-depth");
+            args.push("8");
+            args.push("rgba:-");
+            let rgba = run_command(MAGICK_COMMAND, &args, None)?;
+            half_blocks(&rgba, width as usize).into_bytes()
+        }
+    };
+    output
+        .write_all(&preview_bytes)
+        .and_then(|_| writeln!(output))
+        .map_err(|_| {
+            FolderifyError::General(GeneralError {
+                message: "Could not print the preview.".into(),
+            })
+        })
+}
+
+fn kitty_image(png: &[u8]) -> Vec<u8> {
+    let encoded = base64::encode(png);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+    let mut image = Vec::<u8>::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        let control = match i {
+            0 => format!("a=T,f=100,m={}", more),
+            _ => format!("m={}", more),
+        };
+        image.extend(format!("\x1b_G{};", control).as_bytes());
+        image.extend(*chunk);
+        image.extend(b"\x1b\\");
+    }
+    image
+}
+
+// https://iterm2.com/documentation-images.html
+fn iterm2_image(png: &[u8]) -> Vec<u8> {
+    format!(
+        "\x1b]1337;File=inline=1;size={}:{}\x07",
+        png.len(),
+        base64::encode(png)
+    )
+    .into_bytes()
+}
+
+/// Draws two rows of pixels per line of text, using 24-bit color.
+fn half_blocks(rgba: &[u8], width: usize) -> String {
+    let pixel = |x: usize, y: usize| -> Option<&[u8]> {
+        let offset = (y * width + x) * 4;
+        rgba.get(offset..offset + 4)
+            .filter(|pixel| pixel[3] >= BLOCKS_ALPHA_THRESHOLD)
+    };
+    let height = rgba.len() / 4 / width;
+    let mut text = String::new();
+    for y in (0..height).step_by(2) {
+        for x in 0..width {
+            let bottom = match y + 1 < height {
+                true => pixel(x, y + 1),
+                false => None,
+            };
+            match (pixel(x, y), bottom) {
+                (None, None) => text.push(' '),
+                (Some(top), None) => text.push_str(&format!(
+                    "\x1b[38;2;{};{};{}m▀\x1b[0m",
+                    top[0], top[1], top[2]
+                )),
+                (None, Some(bottom)) => text.push_str(&format!(
+                    "\x1b[38;2;{};{};{}m▄\x1b[0m",
+                    bottom[0], bottom[1], bottom[2]
+                )),
+                (Some(top), Some(bottom)) => text.push_str(&format!(
+                    "\x1b[38;2;{};{};{};48;2;{};{};{}m▀\x1b[0m",
+                    top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+                )),
+            }
+        }
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        args::PreviewProtocol,
+        preview::{detect_protocol, half_blocks, kitty_image},
+    };
+
+    #[test]
+    fn test_detect_protocol() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert_eq!(
+            detect_protocol(env(&[("TERM", "xterm-kitty")])),
+            PreviewProtocol::Kitty
+        );
+        assert_eq!(
+            detect_protocol(env(&[
+                ("TERM", "xterm-256color"),
+                ("LC_TERMINAL", "iTerm2")
+            ])),
+            PreviewProtocol::Iterm2
+        );
+        assert_eq!(
+            detect_protocol(env(&[("TERM", "foot")])),
+            PreviewProtocol::Sixel
+        );
+        assert_eq!(
+            detect_protocol(env(&[("TERM", "xterm-256color")])),
+            PreviewProtocol::Blocks
+        );
+    }
+
+    #[test]
+    fn test_kitty_image() {
+        assert_eq!(kitty_image(b"foo"), b"\x1b_Ga=T,f=100,m=0;Zm9v\x1b\\");
+        let chunked = String::from_utf8(kitty_image(&[0; 4000])).unwrap();
+        assert_eq!(chunked.matches("\x1b_G").count(), 2);
+        assert!(chunked.starts_with("\x1b_Ga=T,f=100,m=1;"));
+        assert!(chunked.contains("\x1b_Gm=0;"));
+    }
+
+    #[test]
+    fn test_half_blocks() {
+        let transparent = [0, 0, 0, 0];
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        // 2x3 pixels: the last row only has a top half.
+        let rgba = [red, transparent, blue, red, transparent, blue].concat();
+        assert_eq!(
+            half_blocks(&rgba, 2),
+            "\x1b[38;2;255;0;0;48;2;0;0;255m▀\x1b[0m\x1b[38;2;255;0;0m▄\x1b[0m\n \x1b[38;2;0;0;255m▀\x1b[0m\n"
+        );
+    }
+}
@@ -40,6 +40,7 @@ impl Display for RGBColor {
     }
 }
 
+#[derive(Clone)]
 pub struct Offset {
     pub x: i32,
     pub y: i32,
@@ -0,0 +1,351 @@
+use std::{
+    fs,
+    io::{stderr, stdout, Write},
+    path::{Path, PathBuf},
+};
+
+use indicatif::MultiProgress;
+
+use crate::{
+    args::{ColorScheme, IconVariant, Options},
+    command::{run_command, OPEN_COMMAND},
+    error::{FolderifyError, GeneralError},
+    icon_conversion::{self, IconResolution, RenderMonitor, WorkingDir},
+    jobs::{run_jobs, Job},
+    magick::CommandArgs,
+    output_paths::{with_name_suffix, FinalOutputPaths, PotentialOutputPaths},
+    preview::print_preview,
+    primitives::Dimensions,
+    resources::IconInputs,
+    status,
+};
+
+/// Renders (and assigns) the icon for the given options.
+pub fn render(options: &Options, monitor: &RenderMonitor) -> Result<(), FolderifyError> {
+    let potential_output_paths = PotentialOutputPaths::new(options);
+
+    status!(
+        "[{}] Using folder style: {}",
+        options.mask_path.display(),
+        options
+            .folder_styles
+            .iter()
+            .map(|folder_style| folder_style.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+    status!(
+        "[{}] Using color scheme: {}",
+        options.mask_path.display(),
+        options
+            .color_schemes
+            .iter()
+            .map(|color_scheme| color_scheme.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+
+    for mask_override in &options.mask_overrides {
+        status!(
+            "[{}] Using mask for {}: {}",
+            options.mask_path.display(),
+            mask_override.resolution,
+            mask_override.path.display()
+        );
+    }
+
+    let working_dir = WorkingDir::new();
+    if options.debug {
+        working_dir.open_in_finder()?;
+    }
+
+    let multi_progress_bar = match options.show_progress {
+        true => Some(MultiProgress::new()),
+        false => None,
+    };
+
+    let input_icon_conversion = working_dir.icon_conversion(
+        icon_conversion::ProgressBarType::Input,
+        "(Input)",
+        multi_progress_bar.clone(),
+        monitor,
+        options,
+    );
+    let full_mask_path = input_icon_conversion.full_mask(
+        options,
+        &Dimensions {
+            width: 768,
+            height: 384,
+        },
+    )?;
+
+    let all_final_output_paths: Vec<FinalOutputPaths> = options
+        .variants()
+        .iter()
+        .map(|variant| potential_output_paths.finalize(options, &working_dir, variant))
+        .collect::<Result<_, _>>()?;
+    let primary_variant = options.primary_variant();
+    let final_output_paths = all_final_output_paths
+        .iter()
+        .find(|final_output_paths| final_output_paths.variant == primary_variant)
+        .unwrap();
+
+    let rendered_path = |paths: &FinalOutputPaths, resolution: &IconResolution| {
+        let resolution = resolution.rendered_resolution();
+        match resolution.is_iconset_resolution() {
+            true => paths.iconset_dir.join(resolution.icon_file()),
+            false => working_dir.custom_size_path(&paths.variant, &resolution),
+        }
+    };
+
+    let mut jobs = Vec::<Job<IconResolution>>::new();
+    for variant_output_paths in &all_final_output_paths {
+        let variant = &variant_output_paths.variant;
+        for resolution in IconResolution::values()
+            .into_iter()
+            .chain(options.custom_resolutions())
+        {
+            let stage_description = match &variant.name_suffix {
+                Some(name_suffix) => format!("{}.{}", resolution, name_suffix),
+                None => resolution.to_string(),
+            };
+            let icon_conversion = working_dir.icon_conversion(
+                icon_conversion::ProgressBarType::Conversion,
+                &stage_description,
+                multi_progress_bar.clone(),
+                monitor,
+                options,
+            );
+            let options = options.clone();
+            let full_mask_path = full_mask_path.clone();
+            let output_path = rendered_path(variant_output_paths, &resolution);
+            let icon_inputs = IconInputs {
+                folder_style: variant.folder_style,
+                color_scheme: variant.color_scheme,
+                resolution,
+                empty_folder: options.empty_folder,
+            };
+            jobs.push(Box::new(move || {
+                icon_conversion.icon(&options, &full_mask_path, &output_path, &icon_inputs)?;
+                Ok(icon_inputs.resolution)
+            }));
+        }
+    }
+
+    let output_without_icns = match (&options.target, &options.output_icns) {
+        (None, None) => options
+            .output_iconset
+            .as_ref()
+            .or(options.output_icon_theme.as_ref())
+            .or(options.output_xcassets.as_ref())
+            .or(options.output_preview.as_ref())
+            .or(options
+                .output_animation
+                .as_ref()
+                .map(|(output_animation, _)| output_animation))
+            .or(options
+                .output_pngs
+                .first()
+                .map(|(output_png, _)| output_png)),
+        _ => None,
+    };
+
+    // Deduplicate this `match` with the one that happens after handle joining.
+    let output_progress_bar_type = match output_without_icns {
+        Some(_) => icon_conversion::ProgressBarType::OutputIcns,
+        None => icon_conversion::ProgressBarType::OutputWithAssignment,
+    };
+    let output_icon_conversion = working_dir.icon_conversion(
+        output_progress_bar_type,
+        "(Output)",
+        multi_progress_bar,
+        monitor,
+        options,
+    );
+    output_icon_conversion.step_unincremented("Waiting…");
+
+    // Results are checked in order, so that the output is the same for every run.
+    for result in run_jobs(jobs) {
+        let resolution = result?;
+        if options.verbose {
+            status!("[{}] {}", options.mask_path.display(), resolution);
+        }
+    }
+
+    if let Some(output_icon_theme) = &options.output_icon_theme {
+        output_icon_conversion.to_icon_theme(
+            options,
+            |resolution| rendered_path(final_output_paths, resolution),
+            output_icon_theme,
+        )?;
+    }
+
+    if let Some(output_xcassets) = &options.output_xcassets {
+        let iconset_dirs: Vec<(Option<ColorScheme>, &Path)> = all_final_output_paths
+            .iter()
+            .filter(|paths| paths.variant.folder_style == primary_variant.folder_style)
+            .map(|paths| {
+                let appearance = match options.color_schemes.len() {
+                    1 => None,
+                    _ => Some(paths.variant.color_scheme),
+                };
+                (appearance, paths.iconset_dir.as_path())
+            })
+            .collect();
+        output_icon_conversion.to_xcassets(options, &iconset_dirs, output_xcassets)?;
+    }
+
+    if let Some(output_preview) = &options.output_preview {
+        let variant_iconsets: Vec<(&IconVariant, &Path)> = all_final_output_paths
+            .iter()
+            .map(|paths| (&paths.variant, paths.iconset_dir.as_path()))
+            .collect();
+        output_icon_conversion.to_contact_sheet(options, &variant_iconsets, output_preview)?;
+    }
+
+    if let Some((output_animation, format)) = &options.output_animation {
+        output_icon_conversion.to_animation(
+            options,
+            &final_output_paths.iconset_dir,
+            output_animation,
+            *format,
+        )?;
+    }
+
+    if !options.output_pngs.is_empty() {
+        let mut rendered_pngs = Vec::<(PathBuf, PathBuf)>::new();
+        for paths in &all_final_output_paths {
+            for (output_png, resolution) in &options.output_pngs {
+                rendered_pngs.push((
+                    rendered_path(paths, resolution),
+                    with_name_suffix(output_png, &paths.variant.name_suffix),
+                ));
+            }
+        }
+        output_icon_conversion.to_pngs(options, &rendered_pngs)?;
+    }
+
+    let reveal_path = match output_without_icns {
+        Some(output_iconset) => {
+            // TODO: avoid `.icns assignment entirely?
+            // TODO: Change the number of output steps?
+            output_iconset
+        }
+        None => {
+            // Only the primary variant is assigned to the target, so the others only need an `.icns` file if it is an output.
+            let all_icns_are_outputs = potential_output_paths.icns_path.is_some();
+            for paths in all_final_output_paths
+                .iter()
+                .filter(|paths| !options.is_embedded_dark_variant(&paths.variant))
+            {
+                let icns_is_assigned =
+                    paths.variant == primary_variant && options.set_icon_using.assigns_icns();
+                if all_icns_are_outputs || icns_is_assigned {
+                    output_icon_conversion.to_icns(
+                        options,
+                        &paths.iconset_dir,
+                        &paths.icns_path,
+                    )?;
+                    // For `--icns-dark-variant`.
+                    let embedded_dark_paths = all_final_output_paths.iter().find(|dark_paths| {
+                        options.is_embedded_dark_variant(&dark_paths.variant)
+                            && dark_paths.variant.folder_style == paths.variant.folder_style
+                    });
+                    if let Some(dark_paths) = embedded_dark_paths {
+                        output_icon_conversion.to_icns(
+                            options,
+                            &dark_paths.iconset_dir,
+                            &dark_paths.icns_path,
+                        )?;
+                        output_icon_conversion.embed_dark_icns(
+                            options,
+                            &paths.icns_path,
+                            &dark_paths.icns_path,
+                        )?;
+                    }
+                }
+            }
+
+            match (&options.target, options.set_icon_using.assigns_icns()) {
+                (Some(target), true) => {
+                    output_icon_conversion.assign_icns(
+                        options,
+                        &final_output_paths.icns_path,
+                        target,
+                    )?;
+                    target
+                }
+                (None, true) if options.icns_to_stdout() => &final_output_paths.icns_path,
+                (None, true) => {
+                    for paths in all_final_output_paths
+                        .iter()
+                        .filter(|paths| !options.is_embedded_dark_variant(&paths.variant))
+                    {
+                        output_icon_conversion.assign_icns(
+                            options,
+                            &paths.icns_path,
+                            &paths.icns_path,
+                        )?;
+                    }
+                    &final_output_paths.icns_path
+                }
+                (Some(target), false) => {
+                    // Freedesktop assignment uses a `.png` rather than the `.icns` file.
+                    output_icon_conversion.assign_png(
+                        options,
+                        &final_output_paths
+                            .iconset_dir
+                            .join(IconResolution::Retina256.icon_file()),
+                        target,
+                    )?;
+                    target
+                }
+                (None, false) if potential_output_paths.icns_path.is_none() => {
+                    &final_output_paths.iconset_dir
+                }
+                (None, false) => &final_output_paths.icns_path,
+            }
+        }
+    };
+
+    if let Some(preview) = &options.preview {
+        // Keep stdout clean for the `.icns` file.
+        match options.icns_to_stdout() {
+            true => print_preview(&final_output_paths.iconset_dir, preview, &mut stderr())?,
+            false => print_preview(&final_output_paths.iconset_dir, preview, &mut stdout())?,
+        }
+    }
+
+    if options.icns_to_stdout() {
+        let icns = fs::read(&final_output_paths.icns_path).map_err(|_| {
+            FolderifyError::General(GeneralError {
+                message: "Could not read the `.icns` file.".into(),
+            })
+        })?;
+        stdout().write_all(&icns).map_err(|_| {
+            FolderifyError::General(GeneralError {
+                message: "Could not write the `.icns` file to stdout.".into(),
+            })
+        })?;
+    }
+
+    for paths in &all_final_output_paths {
+        paths.commit()?;
+    }
+
+    if options.reveal {
+        match options.show_progress {
+            true => output_icon_conversion.step_unincremented("Revealing in Finder…"),
+            false => status!("Revealing in Finder…"),
+        }
+        let mut args = CommandArgs::new();
+        args.push("-R");
+        args.push_path(&final_output_paths.committed_path(reveal_path));
+        run_command(OPEN_COMMAND, &args, None)?;
+    }
+
+    if options.debug {
+        working_dir.release();
+    }
+    Ok(())
+}
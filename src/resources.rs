@@ -29,9 +29,10 @@ pub fn get_folder_icon(icon_inputs: &IconInputs) -> &'static [u8] {
             (ColorScheme::Dark, FolderStyle::BigSur, _) => "GenericFolderIcon.BigSur.dark.iconset",
             (_, FolderStyle::Tahoe, true) => "GenericFolderIcon.empty.Tahoe.iconset",
             (_, FolderStyle::Tahoe, false) => "GenericFolderIcon.non-empty.Tahoe.iconset",
+            (_, FolderStyle::Adwaita, _) => "GenericFolderIcon.Adwaita.iconset",
         },
     );
-    path.push(icon_inputs.resolution.icon_file());
+    path.push(icon_inputs.resolution.template_resolution().icon_file());
     RESOURCES_DIR.get_file(&path).unwrap().contents()
 }
 
@@ -41,7 +42,13 @@ pub fn get_badge_icon(badge: Badge, resolution: &IconResolution) -> &'static [u8
     path.push(match badge {
         Badge::Alias => "AliasBadgeIcon.iconset",
         Badge::Locked => "LockedBadgeIcon.iconset",
+        Badge::ReadOnly => "ReadOnlyBadgeIcon.iconset",
+        Badge::Shared => "SharedBadgeIcon.iconset",
+        Badge::Cloud => "CloudBadgeIcon.iconset",
+        Badge::Warning => "WarningBadgeIcon.iconset",
+        Badge::Checkmark => "CheckmarkBadgeIcon.iconset",
+        Badge::Private => "PrivateBadgeIcon.iconset",
     });
-    path.push(resolution.icon_file());
+    path.push(resolution.template_resolution().icon_file());
     RESOURCES_DIR.get_file(&path).unwrap().contents()
 }
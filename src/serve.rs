@@ -0,0 +1,595 @@
+use std::{
+    fs,
+    io::Read,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{
+        mpsc::{sync_channel, Receiver, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use serde_json::{json, Map, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    args::{try_options_from, Options, ServeOptions},
+    error::FolderifyError,
+    icon_conversion::IconResolution,
+    interrupt::TempDir,
+    json_flags::{flags_from_json, has_text_mask},
+    render::render,
+    status::{set_status_destination, StatusDestination},
+    zip::stored_zip,
+};
+
+const RENDER_PATH: &str = "/render";
+const MAX_REQUEST_BYTES: u64 = 64 * 1024 * 1024;
+// Requests beyond this (per worker) are rejected instead of waiting.
+const MAX_QUEUED_REQUESTS_PER_WORKER: usize = 4;
+const DEFAULT_PNG_SIZE: IconResolution = IconResolution::Retina512;
+
+/// Flags that can be passed in the `options` JSON of a render request, e.g. `{"folder-style": "tahoe"}`.
+/// Flags that refer to files or outputs on the server are not supported.
+const RENDER_OPTION_FLAGS: &[&str] = &[
+    "text",
+    "emoji",
+    "macOS",
+    "folder-style",
+    "empty-folder",
+    "color-scheme",
+    "no-trim",
+    "mode",
+    "no-shadow",
+    "mask-scale",
+    "mask-offset-x",
+    "mask-offset-y",
+    "mask-anchor",
+    "badge",
+    "icns-legacy-chunks",
+    "icns-dark-variant",
+];
+
+pub fn serve(serve_options: &ServeOptions) {
+    set_status_destination(StatusDestination::None);
+
+    let address = format!("{}:{}", serve_options.host, serve_options.port);
+    let server = match Server::http(&address) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Error: could not listen on {}: {}", address, e);
+            std::process::exit(1);
+        }
+    };
+    println!("Listening on http://{}{}", address, RENDER_PATH);
+
+    let workers = serve_options.workers as usize;
+    let (sender, receiver) = sync_channel::<Request>(workers * MAX_QUEUED_REQUESTS_PER_WORKER);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..workers {
+        let receiver = receiver.clone();
+        thread::spawn(move || worker(receiver));
+    }
+
+    for request in server.incoming_requests() {
+        if let Err(TrySendError::Full(request)) = sender.try_send(request) {
+            respond(
+                request,
+                Err(ServeError::new(
+                    503,
+                    "busy",
+                    "Too many render requests are queued. Try again later.",
+                )),
+            );
+        }
+    }
+}
+
+fn worker(receiver: Arc<Mutex<Receiver<Request>>>) {
+    loop {
+        let request = match receiver.lock().unwrap().recv() {
+            Ok(request) => request,
+            Err(_) => return,
+        };
+        // Keep the worker alive if a render panics. `tiny_http` responds with a 500 when the request is dropped.
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| handle_request(request)));
+    }
+}
+
+fn handle_request(mut request: Request) {
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_owned();
+    let result = match (request.method(), path.as_str()) {
+        (Method::Post, RENDER_PATH) => render_request(&mut request),
+        (_, RENDER_PATH) => Err(ServeError::new(
+            405,
+            "method_not_allowed",
+            "Use `POST` to render an icon.",
+        )),
+        _ => Err(ServeError::new(
+            404,
+            "not_found",
+            &format!("Unknown path. Use `POST {}`.", RENDER_PATH),
+        )),
+    };
+    println!(
+        "{} {} => {}",
+        request.method(),
+        path,
+        match &result {
+            Ok(rendered) => format!(
+                "200 ({}, {} bytes)",
+                rendered.content_type,
+                rendered.body.len()
+            ),
+            Err(e) => format!("{} ({})", e.status_code, e.message),
+        }
+    );
+    respond(request, result);
+}
+
+struct RenderedResponse {
+    body: Vec<u8>,
+    content_type: &'static str,
+}
+
+fn respond(request: Request, result: Result<RenderedResponse, ServeError>) {
+    let (status_code, body, content_type) = match result {
+        Ok(rendered) => (200, rendered.body, rendered.content_type),
+        Err(e) => (
+            e.status_code,
+            e.to_json().to_string().into_bytes(),
+            "application/json",
+        ),
+    };
+    let response = Response::from_data(body)
+        .with_status_code(status_code)
+        .with_header(Header::from_bytes("Content-Type", content_type).unwrap());
+    // The client may have disconnected, in which case there is no one to tell.
+    let _ = request.respond(response);
+}
+
+fn render_request(request: &mut Request) -> Result<RenderedResponse, ServeError> {
+    let content_type = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Content-Type"))
+        .map(|header| header.value.as_str().to_owned())
+        .unwrap_or_default();
+    let mut body = Vec::<u8>::new();
+    request
+        .as_reader()
+        .take(MAX_REQUEST_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|_| ServeError::invalid_request("Could not read the request body."))?;
+    if body.len() as u64 > MAX_REQUEST_BYTES {
+        return Err(ServeError::new(
+            413,
+            "too_large",
+            "The request body is too large.",
+        ));
+    }
+    render_multipart(&content_type, &body)
+}
+
+fn render_multipart(content_type: &str, body: &[u8]) -> Result<RenderedResponse, ServeError> {
+    let parts = parse_multipart(content_type, body)
+        .map_err(|message| ServeError::invalid_request(&message))?;
+    let options_json = match parts.iter().find(|part| part.name == "options") {
+        Some(part) => serde_json::from_slice::<Value>(&part.data)
+            .map_err(|e| ServeError::invalid_request(&format!("Invalid options JSON: {}", e)))?,
+        None => json!({}),
+    };
+    let render_options = RenderOptions::from_json(&options_json)
+        .map_err(|message| ServeError::invalid_request(&message))?;
+    let mask_part = parts.iter().find(|part| part.name == "mask");
+    if mask_part.is_some() && has_text_mask(&render_options.flags) {
+        return Err(ServeError::new(
+            400,
+            "invalid_options",
+            "A `mask` cannot be combined with `text` or `emoji`.",
+        ));
+    }
+
+    let temp_dir = TempDir::new()
+        .map_err(|_| ServeError::new(500, "general", "Could not create a temporary folder."))?;
+    let iconset_dir = temp_dir.path().join("icon.iconset");
+    let icns_path = temp_dir.path().join("icon.icns");
+
+    let mut args = vec!["folderify".to_owned()];
+    args.extend(render_options.flags);
+    if let Some(mask_part) = mask_part {
+        let mask_path = temp_dir
+            .path()
+            .join(mask_file_name(mask_part.file_name.as_deref()));
+        fs::write(&mask_path, &mask_part.data)
+            .map_err(|_| ServeError::new(500, "general", "Could not save the mask."))?;
+        args.push(path_arg(&mask_path));
+    }
+    args.push("--no-progress".into());
+    args.push("--output-iconset".into());
+    args.push(path_arg(&iconset_dir));
+    if render_options.format == RenderFormat::Icns {
+        args.push("--output-icns".into());
+        args.push(path_arg(&icns_path));
+    }
+
+    let options = try_options_from(args)
+        .map_err(|message| ServeError::new(400, "invalid_options", message.trim()))?;
+    check_single_icon(&options)?;
+    render(&options, &Default::default())?;
+
+    let read_error = |_| ServeError::new(500, "general", "Could not read the rendered icon.");
+    match render_options.format {
+        RenderFormat::Icns => Ok(RenderedResponse {
+            body: fs::read(&icns_path).map_err(read_error)?,
+            content_type: "image/icns",
+        }),
+        RenderFormat::Iconset => {
+            let mut files = Vec::<(String, Vec<u8>)>::new();
+            for resolution in IconResolution::values().iter().rev() {
+                let icon_file = resolution.icon_file();
+                files.push((
+                    format!("icon.iconset/{}", icon_file),
+                    fs::read(iconset_dir.join(&icon_file)).map_err(read_error)?,
+                ));
+            }
+            Ok(RenderedResponse {
+                body: stored_zip(&files),
+                content_type: "application/zip",
+            })
+        }
+        RenderFormat::Png(resolution) => Ok(RenderedResponse {
+            body: fs::read(iconset_dir.join(resolution.icon_file())).map_err(read_error)?,
+            content_type: "image/png",
+        }),
+    }
+}
+
+/// The response only has room for one icon, although that icon may embed a dark variant (for
+/// `icns-dark-variant`).
+fn check_single_icon(options: &Options) -> Result<(), ServeError> {
+    let icons = options
+        .variants()
+        .iter()
+        .filter(|variant| !options.is_embedded_dark_variant(variant))
+        .count();
+    if icons > 1 {
+        return Err(ServeError::new(
+            400,
+            "invalid_options",
+            "A render request can only produce a single icon (`both` and `all` are not supported).",
+        ));
+    }
+    Ok(())
+}
+
+fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().into()
+}
+
+/// Keeps the extension of the uploaded file (if it is reasonable), so that the format is easy to detect.
+fn mask_file_name(uploaded_file_name: Option<&str>) -> String {
+    let extension = uploaded_file_name
+        .and_then(|file_name| Path::new(file_name).extension())
+        .map(|extension| extension.to_string_lossy().to_string())
+        .filter(|extension| {
+            !extension.is_empty()
+                && extension.len() <= 8
+                && extension.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+    match extension {
+        Some(extension) => format!("mask.{}", extension),
+        None => "mask".into(),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum RenderFormat {
+    Icns,
+    Iconset,
+    Png(IconResolution),
+}
+
+#[derive(Debug, PartialEq)]
+struct RenderOptions {
+    format: RenderFormat,
+    /// Command-line flags for the remaining options.
+    flags: Vec<String>,
+}
+
+impl RenderOptions {
+    /// Reads `format` (`icns`, `iconset`, or `png`) and `size` (for `png`, e.g. `256x256@2x`),
+    /// and converts all other fields to command-line flags.
+    fn from_json(options_json: &Value) -> Result<RenderOptions, String> {
+        let Some(fields) = options_json.as_object() else {
+            return Err("The options must be a JSON object.".into());
+        };
+        let mut fields: Map<String, Value> = fields.clone();
+
+        let size = match fields.remove("size") {
+            None => None,
+            Some(Value::String(size)) => Some(
+                IconResolution::from_name(&size)
+                    .ok_or_else(|| format!("Unknown size: {}", size))?,
+            ),
+            Some(_) => return Err("`size` must be a string.".into()),
+        };
+        let format = match fields.remove("format") {
+            None => RenderFormat::Icns,
+            Some(Value::String(format)) => match format.as_str() {
+                "icns" => RenderFormat::Icns,
+                "iconset" => RenderFormat::Iconset,
+                "png" => RenderFormat::Png(size.unwrap_or(DEFAULT_PNG_SIZE)),
+                _ => return Err(format!("Unknown format: {}", format)),
+            },
+            Some(_) => return Err("`format` must be a string.".into()),
+        };
+        if size.is_some() && !matches!(format, RenderFormat::Png(_)) {
+            return Err("`size` is only supported for the `png` format.".into());
+        }
+
+        let flags = flags_from_json(&fields, |name| RENDER_OPTION_FLAGS.contains(&name))?;
+        Ok(RenderOptions { format, flags })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct MultipartPart {
+    name: String,
+    file_name: Option<String>,
+    data: Vec<u8>,
+}
+
+// https://www.rfc-editor.org/rfc/rfc7578
+fn parse_multipart(content_type: &str, body: &[u8]) -> Result<Vec<MultipartPart>, String> {
+    let boundary = content_type
+        .split(';')
+        .map(str::trim)
+        .find_map(|parameter| parameter.strip_prefix("boundary="))
+        .map(|boundary| boundary.trim_matches('"'))
+        .filter(|_| content_type.trim_start().starts_with("multipart/form-data"))
+        .ok_or("Expected a `multipart/form-data` request.")?;
+    let delimiter = format!("--{}", boundary).into_bytes();
+
+    let mut parts = Vec::<MultipartPart>::new();
+    let mut segments = split_bytes(body, &delimiter).into_iter();
+    // Skip the preamble.
+    segments.next();
+    for segment in segments {
+        if segment.starts_with(b"--") {
+            return Ok(parts);
+        }
+        let segment = segment.strip_prefix(b"\r\n").unwrap_or(segment);
+        let segment = segment.strip_suffix(b"\r\n").unwrap_or(segment);
+        let header_end = find_bytes(segment, b"\r\n\r\n").ok_or("Invalid multipart headers.")?;
+        let headers = String::from_utf8_lossy(&segment[..header_end]);
+        let content_disposition = headers
+            .lines()
+            .find_map(|line| {
+                let (field, value) = line.split_once(':')?;
+                field
+                    .trim()
+                    .eq_ignore_ascii_case("Content-Disposition")
+                    .then_some(value.to_owned())
+            })
+            .ok_or("Missing multipart `Content-Disposition`.")?;
+        let parameter = |key: &str| {
+            content_disposition
+                .split(';')
+                .map(str::trim)
+                .find_map(|parameter| {
+                    let (parameter_key, value) = parameter.split_once('=')?;
+                    (parameter_key == key).then(|| value.trim_matches('"').to_owned())
+                })
+        };
+        parts.push(MultipartPart {
+            name: parameter("name").ok_or("Missing multipart field name.")?,
+            file_name: parameter("filename"),
+            data: segment[header_end + 4..].to_vec(),
+        });
+    }
+    Err("Incomplete multipart body.".into())
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn split_bytes<'a>(bytes: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut segments = Vec::<&[u8]>::new();
+    let mut rest = bytes;
+    while let Some(index) = find_bytes(rest, delimiter) {
+        segments.push(&rest[..index]);
+        rest = &rest[index + delimiter.len()..];
+    }
+    segments.push(rest);
+    segments
+}
+
+#[derive(Debug)]
+struct ServeError {
+    status_code: u16,
+    error_type: &'static str,
+    message: String,
+    details: Map<String, Value>,
+}
+
+impl ServeError {
+    fn new(status_code: u16, error_type: &'static str, message: &str) -> Self {
+        Self {
+            status_code,
+            error_type,
+            message: message.into(),
+            details: Map::new(),
+        }
+    }
+
+    fn invalid_request(message: &str) -> Self {
+        Self::new(400, "invalid_request", message)
+    }
+
+    fn to_json(&self) -> Value {
+        let mut error = self.details.clone();
+        error.insert("type".into(), self.error_type.into());
+        error.insert("message".into(), self.message.clone().into());
+        json!({ "error": error })
+    }
+}
+
+impl From<FolderifyError> for ServeError {
+    fn from(value: FolderifyError) -> Self {
+        let error_type = match &value {
+            FolderifyError::CommandInvalid(_) => "command_invalid",
+            FolderifyError::CommandFailed(_) => "command_failed",
+            FolderifyError::General(_) => "general",
+        };
+        let mut serve_error = ServeError::new(500, error_type, &value.to_string());
+        match value {
+            FolderifyError::CommandInvalid(e) => {
+                serve_error
+                    .details
+                    .insert("command".into(), e.command_name.into());
+            }
+            FolderifyError::CommandFailed(e) => {
+                serve_error
+                    .details
+                    .insert("command".into(), e.command_name.into());
+                serve_error.details.insert(
+                    "stderr".into(),
+                    String::from_utf8_lossy(&e.stderr).into_owned().into(),
+                );
+            }
+            FolderifyError::General(_) => {}
+        }
+        serve_error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        args::try_options_from,
+        error::{CommandFailedError, FolderifyError},
+        icon_conversion::IconResolution,
+        serve::{
+            check_single_icon, mask_file_name, parse_multipart, render_multipart, MultipartPart,
+            RenderFormat, RenderOptions, ServeError,
+        },
+    };
+
+    #[test]
+    fn test_parse_multipart() {
+        let body = b"preamble\r\n--XyZ\r\nContent-Disposition: form-data; name=\"options\"\r\n\r\n{\"format\": \"png\"}\r\n--XyZ\r\nContent-Disposition: form-data; name=\"mask\"; filename=\"logo.svg\"\r\nContent-Type: image/svg+xml\r\n\r\n<svg/>\r\n--XyZ--\r\n";
+        assert_eq!(
+            parse_multipart("multipart/form-data; boundary=XyZ", body),
+            Ok(vec![
+                MultipartPart {
+                    name: "options".into(),
+                    file_name: None,
+                    data: b"{\"format\": \"png\"}".to_vec(),
+                },
+                MultipartPart {
+                    name: "mask".into(),
+                    file_name: Some("logo.svg".into()),
+                    data: b"<svg/>".to_vec(),
+                },
+            ])
+        );
+        assert!(parse_multipart("application/json", body).is_err());
+        assert!(parse_multipart("multipart/form-data; boundary=XyZ", b"--XyZ\r\n").is_err());
+    }
+
+    #[test]
+    fn test_render_options_from_json() {
+        assert_eq!(
+            RenderOptions::from_json(&json!({
+                "format": "png",
+                "size": "32x32@2x",
+                "folder-style": "tahoe",
+                "badge": ["locked", "alias:top-right"],
+                "empty-folder": true,
+                "no-trim": false,
+                "mask-offset-y": -16,
+            })),
+            Ok(RenderOptions {
+                format: RenderFormat::Png(IconResolution::Retina32),
+                flags: vec![
+                    "--badge=locked".into(),
+                    "--badge=alias:top-right".into(),
+                    "--empty-folder".into(),
+                    "--folder-style=tahoe".into(),
+                    "--mask-offset-y=-16".into(),
+                ],
+            })
+        );
+        assert_eq!(
+            RenderOptions::from_json(&json!({})).unwrap().format,
+            RenderFormat::Icns
+        );
+        assert!(RenderOptions::from_json(&json!({"output-icns": "/tmp/x.icns"})).is_err());
+        assert!(RenderOptions::from_json(&json!({"size": "16x16"})).is_err());
+        assert!(RenderOptions::from_json(&json!({"format": "gif"})).is_err());
+        assert!(RenderOptions::from_json(&json!(["icns"])).is_err());
+    }
+
+    #[test]
+    fn test_render_multipart() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"options\"\r\n\r\n{\"text\": \"API\"}\r\n--XyZ\r\nContent-Disposition: form-data; name=\"mask\"; filename=\"logo.png\"\r\n\r\npng\r\n--XyZ--\r\n";
+        let serve_error = render_multipart("multipart/form-data; boundary=XyZ", body)
+            .err()
+            .unwrap();
+        assert_eq!(serve_error.status_code, 400);
+        assert_eq!(serve_error.error_type, "invalid_options");
+    }
+
+    #[test]
+    fn test_check_single_icon() {
+        let options = |flags: &[&str]| {
+            let mut args = vec!["folderify", "--text=A", "--folder-style=big-sur"];
+            args.extend(flags);
+            args.extend(["--output-iconset", "icon.iconset"]);
+            try_options_from(args).unwrap()
+        };
+        assert!(check_single_icon(&options(&[])).is_ok());
+        assert!(check_single_icon(&options(&["--icns-dark-variant"])).is_ok());
+        assert!(check_single_icon(&options(&["--color-scheme=both"])).is_err());
+    }
+
+    #[test]
+    fn test_mask_file_name() {
+        assert_eq!(mask_file_name(Some("logo.svg")), "mask.svg");
+        assert_eq!(mask_file_name(Some("../../etc/passwd")), "mask");
+        assert_eq!(mask_file_name(Some("a.b/c")), "mask");
+        assert_eq!(mask_file_name(None), "mask");
+    }
+
+    #[test]
+    fn test_serve_error_json() {
+        let serve_error: ServeError = FolderifyError::CommandFailed(CommandFailedError {
+            command_name: "magick".into(),
+            stderr: b"bad image".to_vec(),
+        })
+        .into();
+        assert_eq!(
+            serve_error.to_json(),
+            json!({
+                "error": {
+                    "type": "command_failed",
+                    "message": "`magick` failed.",
+                    "command": "magick",
+                    "stderr": "bad image",
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatusDestination {
+    Stdout,
+    /// Used when stdout is used for output (e.g. `--output-icns -`).
+    Stderr,
+    /// Used when rendering for `folderify serve`.
+    None,
+}
+
+static STATUS_DESTINATION: AtomicU8 = AtomicU8::new(StatusDestination::Stdout as u8);
+
+pub fn set_status_destination(status_destination: StatusDestination) {
+    STATUS_DESTINATION.store(status_destination as u8, Ordering::Relaxed);
+}
+
+pub fn status_destination() -> StatusDestination {
+    match STATUS_DESTINATION.load(Ordering::Relaxed) {
+        0 => StatusDestination::Stdout,
+        1 => StatusDestination::Stderr,
+        _ => StatusDestination::None,
+    }
+}
+
+/// Like `println!`, but follows the current `StatusDestination`.
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {
+        match $crate::status::status_destination() {
+            $crate::status::StatusDestination::Stdout => println!($($arg)*),
+            $crate::status::StatusDestination::Stderr => eprintln!($($arg)*),
+            $crate::status::StatusDestination::None => {}
+        }
+    };
+}
@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+
+// Used for `--emoji` when no `--font` is specified. These are color bitmap fonts, which
+// ImageMagick's `label:` can't render, so they are rendered using `pango:` (by family name).
+#[cfg(target_os = "macos")]
+const DEFAULT_EMOJI_FONT_FAMILY: &str = "Apple Color Emoji";
+#[cfg(not(target_os = "macos"))]
+const DEFAULT_EMOJI_FONT_FAMILY: &str = "Noto Color Emoji";
+
+/// Text that is rendered as the mask, instead of reading a mask file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextMask {
+    pub text: String,
+    pub emoji: bool,
+    pub font: Option<PathBuf>,
+}
+
+impl TextMask {
+    /// Whether the text is rendered using the system emoji font, which requires ImageMagick's
+    /// (optional) Pango support.
+    pub fn uses_pango(&self) -> bool {
+        self.emoji && self.font.is_none()
+    }
+
+    /// The text as an ImageMagick `label:` (or `pango:`) argument.
+    pub fn label(&self) -> String {
+        match self.uses_pango() {
+            true => format!(
+                "pango:<span font_family=\"{}\">{}</span>",
+                DEFAULT_EMOJI_FONT_FAMILY,
+                escape_label_text(&escape_markup(&self.text))
+            ),
+            false => format!("label:{}", escape_label_text(&self.text)),
+        }
+    }
+
+    /// Stands in for the mask path when naming outputs, e.g. `API` (which is written to `API.icns`).
+    pub fn file_stem(&self) -> String {
+        let mut file_stem = String::new();
+        for c in self.text.trim().chars() {
+            match c {
+                '/' | '\\' | ':' | '.' => file_stem.push('-'),
+                c if c.is_whitespace() => {
+                    if !file_stem.ends_with('-') {
+                        file_stem.push('-')
+                    }
+                }
+                c => file_stem.push(c),
+            }
+        }
+        match file_stem.is_empty() {
+            true => "text".into(),
+            false => file_stem,
+        }
+    }
+}
+
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// ImageMagick reads the text from a file if it starts with `@`, and expands `%` escapes.
+fn escape_label_text(text: &str) -> String {
+    let escaped = text.replace('%', "%%");
+    match escaped.starts_with('@') {
+        true => format!("\\{}", escaped),
+        false => escaped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::text_mask::TextMask;
+
+    fn text_mask(text: &str) -> TextMask {
+        TextMask {
+            text: text.into(),
+            emoji: false,
+            font: None,
+        }
+    }
+
+    #[test]
+    fn test_label() {
+        assert_eq!(text_mask("API").label(), "label:API");
+        assert_eq!(text_mask("100%").label(), "label:100%%");
+        assert_eq!(text_mask("@home").label(), "label:\\@home");
+        assert_eq!(text_mask("A\nB").label(), "label:A\nB");
+
+        let emoji = |font: Option<PathBuf>| TextMask {
+            text: "🚀<&>%".into(),
+            emoji: true,
+            font,
+        };
+        assert!(emoji(None).label().starts_with("pango:<span font_family="));
+        assert!(emoji(None).label().ends_with(">🚀&lt;&amp;&gt;%%</span>"));
+        assert_eq!(emoji(Some("emoji.ttf".into())).label(), "label:🚀<&>%%");
+    }
+
+    #[test]
+    fn test_file_stem() {
+        assert_eq!(text_mask("API").file_stem(), "API");
+        assert_eq!(text_mask("v1.2").file_stem(), "v1-2");
+        assert_eq!(text_mask("Two\n  lines").file_stem(), "Two-lines");
+        assert_eq!(text_mask("a/b").file_stem(), "a-b");
+        assert_eq!(text_mask("🚀").file_stem(), "🚀");
+        assert_eq!(text_mask(" ").file_stem(), "text");
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::{Path, PathBuf};
+
+use crate::{args::ColorScheme, icon_conversion::IconResolution};
+
+// https://developer.apple.com/library/archive/documentation/Xcode/Reference/xcode_ref-Asset_Catalog_Format/
+const CONTENTS_JSON_FILE_NAME: &str = "Contents.json";
+const CONTENTS_JSON_INFO: &str =
+    "\"info\" : {\n    \"author\" : \"xcode\",\n    \"version\" : 1\n  }";
+
+pub fn iconset_dir(xcassets_dir: &Path, asset_name: &str) -> PathBuf {
+    xcassets_dir.join(format!("{}.iconset", asset_name))
+}
+
+pub fn contents_json_path(dir: &Path) -> PathBuf {
+    dir.join(CONTENTS_JSON_FILE_NAME)
+}
+
+/// `Contents.json` for the catalog root, which only contains the `info` field.
+pub fn catalog_contents_json() -> String {
+    format!("{{\n  {}\n}}\n", CONTENTS_JSON_INFO)
+}
+
+/// File name for a resolution within the asset. Variants are suffixed with their color scheme,
+/// except when only a single color scheme is included.
+pub fn image_file_name(resolution: &IconResolution, appearance: Option<ColorScheme>) -> String {
+    match appearance {
+        Some(ColorScheme::Dark) => format!("icon_{}.dark.png", resolution),
+        _ => resolution.icon_file(),
+    }
+}
+
+/// Lists every resolution (at every appearance, if more than one is included).
+/// `None` stands for the default appearance when a single color scheme is included.
+pub fn iconset_contents_json(appearances: &[Option<ColorScheme>]) -> String {
+    let mut images = Vec::<String>::new();
+    for resolution in IconResolution::values().iter().rev() {
+        for appearance in appearances {
+            let appearances_field = match appearance {
+                Some(ColorScheme::Dark) => "      \"appearances\" : [\n        {\n          \"appearance\" : \"luminosity\",\n          \"value\" : \"dark\"\n        }\n      ],\n",
+                _ => "",
+            };
+            images.push(format!(
+                "    {{\n{}      \"filename\" : \"{}\",\n      \"idiom\" : \"mac\",\n      \"scale\" : \"{}x\",\n      \"size\" : \"{}x{}\"\n    }}",
+                appearances_field,
+                image_file_name(resolution, *appearance),
+                resolution.scale(),
+                resolution.base_size(),
+                resolution.base_size(),
+            ));
+        }
+    }
+    format!(
+        "{{\n  \"images\" : [\n{}\n  ],\n  {}\n}}\n",
+        images.join(",\n"),
+        CONTENTS_JSON_INFO
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{args::ColorScheme, xcassets::iconset_contents_json};
+
+    #[test]
+    fn test_iconset_contents_json() {
+        let single = iconset_contents_json(&[None]);
+        assert_eq!(single.matches("\"idiom\" : \"mac\"").count(), 10);
+        assert!(single.contains("\"filename\" : \"icon_16x16@2x.png\",\n      \"idiom\" : \"mac\",\n      \"scale\" : \"2x\",\n      \"size\" : \"16x16\""));
+        assert!(!single.contains("appearances"));
+
+        let both = iconset_contents_json(&[Some(ColorScheme::Light), Some(ColorScheme::Dark)]);
+        assert_eq!(both.matches("\"idiom\" : \"mac\"").count(), 20);
+        assert_eq!(both.matches("\"appearances\"").count(), 10);
+        assert!(both.contains("\"filename\" : \"icon_512x512@2x.dark.png\""));
+    }
+}
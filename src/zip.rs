@@ -0,0 +1,120 @@
+// https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT
+// `.png` files are already compressed, so files are stored without compression.
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const VERSION: u16 = 20;
+// Bit 11: file names are UTF-8.
+const GENERAL_PURPOSE_FLAGS: u16 = 1 << 11;
+const COMPRESSION_METHOD_STORED: u16 = 0;
+// 1980-01-01 00:00, the earliest DOS date.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// Creates a `.zip` archive containing the given files (by path within the archive).
+pub fn stored_zip(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut zip = Vec::<u8>::new();
+    let mut central_directory = Vec::<u8>::new();
+    for (name, contents) in files {
+        let offset = zip.len() as u32;
+        let crc = crc32(contents);
+        let size = contents.len() as u32;
+
+        push_u32(&mut zip, LOCAL_FILE_HEADER_SIGNATURE);
+        push_common_header_fields(&mut zip, name, crc, size);
+        push_u16(&mut zip, 0); // Extra field length
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend_from_slice(contents);
+
+        push_u32(&mut central_directory, CENTRAL_DIRECTORY_HEADER_SIGNATURE);
+        push_u16(&mut central_directory, VERSION); // Version made by
+        push_common_header_fields(&mut central_directory, name, crc, size);
+        push_u16(&mut central_directory, 0); // Extra field length
+        push_u16(&mut central_directory, 0); // File comment length
+        push_u16(&mut central_directory, 0); // Disk number start
+        push_u16(&mut central_directory, 0); // Internal file attributes
+        push_u32(&mut central_directory, 0); // External file attributes
+        push_u32(&mut central_directory, offset);
+        central_directory.extend_from_slice(name.as_bytes());
+    }
+
+    let central_directory_offset = zip.len() as u32;
+    zip.extend_from_slice(&central_directory);
+    push_u32(&mut zip, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+    push_u16(&mut zip, 0); // Number of this disk
+    push_u16(&mut zip, 0); // Disk with the central directory
+    push_u16(&mut zip, files.len() as u16);
+    push_u16(&mut zip, files.len() as u16);
+    push_u32(&mut zip, central_directory.len() as u32);
+    push_u32(&mut zip, central_directory_offset);
+    push_u16(&mut zip, 0); // Comment length
+    zip
+}
+
+// Fields shared by the local file header and the central directory header, up to the file name length.
+fn push_common_header_fields(bytes: &mut Vec<u8>, name: &str, crc: u32, size: u32) {
+    push_u16(bytes, VERSION); // Version needed to extract
+    push_u16(bytes, GENERAL_PURPOSE_FLAGS);
+    push_u16(bytes, COMPRESSION_METHOD_STORED);
+    push_u16(bytes, DOS_TIME);
+    push_u16(bytes, DOS_DATE);
+    push_u32(bytes, crc);
+    push_u32(bytes, size); // Compressed size
+    push_u32(bytes, size); // Uncompressed size
+    push_u16(bytes, name.len() as u16);
+}
+
+fn push_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xedb88320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::zip::{crc32, stored_zip};
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn test_stored_zip() {
+        let zip = stored_zip(&[
+            ("a.iconset/icon_16x16.png".into(), b"abc".to_vec()),
+            ("a.iconset/icon_32x32.png".into(), vec![]),
+        ]);
+        assert_eq!(&zip[0..4], b"PK\x03\x04");
+        // Local header (30 bytes) + name (24 bytes) + contents (3 bytes)
+        assert_eq!(&zip[30..54], b"a.iconset/icon_16x16.png");
+        assert_eq!(&zip[54..57], b"abc");
+        let end_of_central_directory = &zip[zip.len() - 22..];
+        assert_eq!(&end_of_central_directory[0..4], b"PK\x05\x06");
+        assert_eq!(&end_of_central_directory[10..12], &[2, 0]);
+        let central_directory_offset =
+            u32::from_le_bytes(end_of_central_directory[16..20].try_into().unwrap()) as usize;
+        assert_eq!(
+            &zip[central_directory_offset..central_directory_offset + 4],
+            b"PK\x01\x02"
+        );
+    }
+}
@@ -29,6 +29,29 @@ test("Help flag", async () => {
   await shellOut(["--help"]);
 });
 
+test("Styles subcommand", async () => {
+  await shellOut(["styles"]);
+});
+
+test("Serve subcommand help", async () => {
+  await shellOut(["serve", "--help"]);
+});
+
+test("Daemon subcommand help", async () => {
+  await shellOut(["daemon", "--help"]);
+});
+
+test("Test that `--folder-style tahoe` is accepted.", async () => {
+  await shellOut([["--folder-style", "tahoe"], EXAMPLES.join("src/apple.png")]);
+});
+
+test("Test that `--preview` is accepted.", async () => {
+  await shellOut([
+    ["--preview", "--preview-protocol", "blocks"],
+    EXAMPLES.join("src/apple.png"),
+  ]);
+});
+
 test("Generate icon file", async () => {
   await shellOut([EXAMPLES.join("src/apple.png")]);
   expect(await EXAMPLES.join("src/apple.icns").existsAsFile()).toBe(true);
@@ -89,14 +112,167 @@ test("Test that `--color-scheme dark` is accepted.", async () => {
   await shellOut([["--color-scheme", "dark"], EXAMPLES.join("src/apple.png")]);
 });
 
+test("Test that `--color-scheme both` writes both variants.", async () => {
+  const tempDir = await Path.makeTempDir();
+  await shellOut([
+    ["--macOS", "15.0"],
+    ["--color-scheme", "both"],
+    ["--output-icns", tempDir.join("apple.icns")],
+    ["--output-iconset", tempDir.join("apple.iconset")],
+    EXAMPLES.join("src/apple.png"),
+  ]);
+  for (const name of ["apple", "apple.dark"]) {
+    expect(await tempDir.join(`${name}.icns`).existsAsFile()).toBe(true);
+    expect(await tempDir.join(`${name}.iconset`).existsAsDir()).toBe(true);
+  }
+});
+
+test("Test that `--macOS all` writes every folder style.", async () => {
+  const tempDir = await Path.makeTempDir();
+  await shellOut([
+    ["--macOS", "all"],
+    ["--color-scheme", "both"],
+    ["--output-icns", tempDir.join("apple.icns")],
+    EXAMPLES.join("src/apple.png"),
+  ]);
+  for (const name of ["apple.big-sur", "apple.big-sur.dark", "apple.tahoe"]) {
+    expect(await tempDir.join(`${name}.icns`).existsAsFile()).toBe(true);
+  }
+});
+
 test("Test that `--no-progress` is accepted.", async () => {
   await shellOut(["--no-progress", EXAMPLES.join("src/apple.png")]);
 });
 
+test("Test that `--jobs` is accepted.", async () => {
+  await shellOut([["--jobs", "1"], EXAMPLES.join("src/apple.png")]);
+});
+
 test("Test that `--badge alias` is accepted.", async () => {
   await shellOut([["--badge", "alias"], EXAMPLES.join("src/apple.png")]);
 });
 
+test("Test that `--text …` works.", async () => {
+  const icnsPath = EXAMPLES.join("./src/text_mask.icns");
+  await shellOut([["--text", "API"], ["--output-icns", icnsPath]]);
+  expect(await icnsPath.exists()).toBe(true);
+});
+
+test("Test that `--emoji …` renders a mask.", async () => {
+  const pngPath = EXAMPLES.join("./src/emoji_mask.png");
+  await shellOut([
+    ["--emoji", "🚀"],
+    ["--mode", "color"],
+    ["--output-png", pngPath],
+    ["--size", "64"],
+  ]);
+  expect(await pngPath.existsAsFile()).toBe(true);
+});
+
+test("Test that multi-line `--emoji …` renders a mask.", async () => {
+  const pngPath = EXAMPLES.join("./src/emoji_lines_mask.png");
+  await shellOut([
+    ["--emoji", "🚀🚀🚀\n🛰️"],
+    ["--output-png", pngPath],
+    ["--size", "512@2x"],
+  ]);
+  expect(await pngPath.existsAsFile()).toBe(true);
+});
+
+test("Test that `--mode color` is accepted.", async () => {
+  await shellOut([["--mode", "color"], EXAMPLES.join("src/apple.png")]);
+  await shellOut([
+    ["--mode", "color"],
+    "--no-shadow",
+    EXAMPLES.join("src/apple.png"),
+  ]);
+});
+
+test("Test that mask placement flags are accepted.", async () => {
+  await shellOut([
+    ["--mask-scale", "0.8"],
+    ["--mask-offset-x", "-16"],
+    ["--mask-offset-y", "16"],
+    ["--mask-anchor", "top-left"],
+    EXAMPLES.join("src/apple.png"),
+  ]);
+});
+
+test("Test that a mask placement past the folder front is rejected.", async () => {
+  expect(
+    await (async () => {
+      const { stderr } = (
+        await cmd([["--mask-scale", "2"], EXAMPLES.join("src/apple.png")])
+      ).spawn({ stdio: ["ignore", "ignore", "pipe"] });
+      return new Response(Readable.from(stderr)).text();
+    })(),
+  ).toMatch("Error: the mask would extend past the front of the folder");
+});
+
+test("Test that `--output-icns -` writes to stdout.", async () => {
+  const { stdout } = (
+    await cmd([["--output-icns", "-"], EXAMPLES.join("src/apple.png")])
+  ).spawn({ stdio: ["ignore", "pipe", "ignore"] });
+  const icns = await new Response(Readable.from(stdout)).arrayBuffer();
+  expect(new TextDecoder().decode(icns.slice(0, 4))).toBe("icns");
+});
+
+test("Test that `--icns-legacy-chunks` adds legacy chunks.", async () => {
+  const { stdout } = (
+    await cmd([
+      ["--output-icns", "-"],
+      "--icns-legacy-chunks",
+      EXAMPLES.join("src/apple.png"),
+    ])
+  ).spawn({ stdio: ["ignore", "pipe", "ignore"] });
+  const icns = await new Response(Readable.from(stdout)).arrayBuffer();
+  const text = new TextDecoder("latin1").decode(icns);
+  for (const chunkType of ["is32", "s8mk", "il32", "l8mk", "it32", "t8mk"]) {
+    expect(text).toContain(chunkType);
+  }
+});
+
+test("Test that `--icns-dark-variant` writes a single `.icns` file.", async () => {
+  const tempDir = await Path.makeTempDir();
+  await shellOut([
+    ["--macOS", "11"],
+    "--icns-dark-variant",
+    ["--output-icns", tempDir.join("apple.icns")],
+    EXAMPLES.join("src/apple.png"),
+  ]);
+  expect(await tempDir.join("apple.icns").existsAsFile()).toBe(true);
+  expect(await tempDir.join("apple.dark.icns").exists()).toBe(false);
+});
+
+test("Test that every built-in badge is accepted.", async () => {
+  for (const badge of [
+    "read-only",
+    "shared",
+    "cloud",
+    "warning",
+    "checkmark",
+    "private",
+  ]) {
+    await shellOut([["--badge", badge], EXAMPLES.join("src/apple.png")]);
+  }
+});
+
+test("Test that multiple positioned badges are accepted.", async () => {
+  await shellOut([
+    ["--badge", "alias"],
+    ["--badge", "locked:top-right"],
+    ["--badge-image", `${EXAMPLES.join("src/apple.png")}:center`],
+    EXAMPLES.join("src/apple.png"),
+  ]);
+});
+
+test("Test that `--badge-image …` is accepted.", async () => {
+  await shellOut([
+    ["--badge-image", EXAMPLES.join("src/apple.png")],
+    EXAMPLES.join("src/apple.png"),
+  ]);
+});
+
 test("Test that `--output-icns …` works.", async () => {
   await shellOut([
     ["--output-icns", EXAMPLES.join("./src/folder_outline_custom_path_1.icns")],
@@ -149,6 +325,83 @@ test("Test that `--output-icns …` and `--output-iconset …` can be used toget
   );
 });
 
+test("Test that `--output-icon-theme …` works.", async () => {
+  const tempDir = await Path.makeTempDir();
+  await shellOut([
+    ["--output-icon-theme", tempDir],
+    ["--icon-name", "folder-apple"],
+    EXAMPLES.join("src/apple.png"),
+  ]);
+  expect(await tempDir.join("index.theme").existsAsFile()).toBe(true);
+  for (const dir of [
+    "16x16",
+    "16x16@2",
+    "24x24",
+    "48x48",
+    "64x64",
+    "512x512",
+    "512x512@2",
+  ]) {
+    expect(
+      await tempDir.join(`${dir}/places/folder-apple.png`).existsAsFile(),
+    ).toBe(true);
+  }
+});
+
+test("Test that `--output-xcassets …` works.", async () => {
+  const tempDir = await Path.makeTempDir();
+  const xcassets = tempDir.join("Assets.xcassets");
+  await shellOut([
+    ["--output-xcassets", xcassets],
+    ["--name", "DocFolder"],
+    EXAMPLES.join("src/apple.png"),
+  ]);
+  expect(await xcassets.join("Contents.json").existsAsFile()).toBe(true);
+  expect(
+    await xcassets.join("DocFolder.iconset/Contents.json").existsAsFile(),
+  ).toBe(true);
+  expect(
+    await xcassets.join("DocFolder.iconset/icon_16x16@2x.png").existsAsFile(),
+  ).toBe(true);
+});
+
+test("Test that `--output-preview …` works.", async () => {
+  const tempDir = await Path.makeTempDir();
+  const sheet = tempDir.join("sheet.png");
+  await shellOut([
+    ["--output-preview", sheet],
+    ["--color-scheme", "both"],
+    EXAMPLES.join("src/apple.png"),
+  ]);
+  expect(await sheet.existsAsFile()).toBe(true);
+});
+
+for (const extension of ["gif", "apng"]) {
+  test(`Test that \`--output-animation ….${extension}\` works.`, async () => {
+    const tempDir = await Path.makeTempDir();
+    const animation = tempDir.join(`apple.${extension}`);
+    await shellOut([
+      ["--output-animation", animation],
+      EXAMPLES.join("src/apple.png"),
+    ]);
+    expect(await animation.existsAsFile()).toBe(true);
+  });
+}
+
+test("Test that `--output-png … --size …` works.", async () => {
+  const tempDir = await Path.makeTempDir();
+  const large = tempDir.join("large.png");
+  const medium = tempDir.join("medium.png");
+  await shellOut([
+    ["--output-png", large],
+    ["--output-png", medium],
+    ["--size", "48"],
+    EXAMPLES.join("src/apple.png"),
+  ]);
+  expect(await large.existsAsFile()).toBe(true);
+  expect(await medium.existsAsFile()).toBe(true);
+});
+
 for (const macOSVersion of ["10.5", "10.8", "10.15"]) {
   test(`Test that known macOS ${macOSVersion} is rejected`, async () => {
     expect(
@@ -177,7 +430,14 @@ for (const macOSVersion of ["10.16", "99.0"]) {
   });
 }
 
-for (const macOSVersion of ["11.0", "12.1", "14.2.1", "26"]) {
+for (const macOSVersion of [
+  "11.0",
+  "12.1",
+  "14.2.1",
+  "26",
+  "sonoma",
+  "tahoe",
+]) {
   test(`Test that known macOS ${macOSVersion} is accepted without a warning`, async () => {
     expect(
       await (async () => {